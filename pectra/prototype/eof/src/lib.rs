@@ -2,27 +2,217 @@
 //! This module defines the structures and a basic parser for EOF contracts
 //! as per EIP-3540.
 
-use std::collections::HashSet;
-use std::convert::TryInto;
+use std::collections::{BTreeSet, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+use thiserror::Error;
 
 pub const EOF_MAGIC: u16 = 0xEF00;
 pub const EOF_VERSION: u8 = 0x01;
+/// EIP-3860/7620: the largest container (the initcode size limit) EOF accepts.
+pub const MAX_CONTAINER_SIZE: usize = 0xC000;
+// EIP-170 runtime code size limit, and the initcode limit of twice that (EIP-3860)
+pub const MAX_CODE_SIZE: usize = 0x6000;
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// Opcode constants grouped by category. Every constant is also re-exported at
+/// the crate root under its existing name.
+pub mod opcodes {
+    pub mod arithmetic {
+        pub const ADD: u8 = 0x01;
+        pub const MUL: u8 = 0x02;
+    }
+
+    pub mod stack {
+        pub const POP: u8 = 0x50;
+    }
+
+    pub mod memory {
+        pub const MLOAD: u8 = 0x51;
+        pub const MSTORE: u8 = 0x52;
+        pub const MSTORE8: u8 = 0x53;
+        pub const MSIZE: u8 = 0x59;
+    }
+
+    pub mod environment {
+        pub const CODESIZE: u8 = 0x38;
+        pub const CODECOPY: u8 = 0x39;
+        pub const EXTCODESIZE: u8 = 0x3B;
+        pub const EXTCODECOPY: u8 = 0x3C;
+        pub const EXTCODEHASH: u8 = 0x3F;
+        pub const GAS: u8 = 0x5A;
+    }
+
+    pub mod push {
+        pub const PUSH0: u8 = 0x5F;
+        pub const PUSH1: u8 = 0x60;
+        pub const PUSH2: u8 = 0x61;
+        pub const PUSH32: u8 = 0x7F;
+    }
+
+    pub mod control_flow {
+        pub const STOP: u8 = 0x00;
+        pub const JUMP: u8 = 0x56;
+        pub const JUMPI: u8 = 0x57;
+        pub const PC: u8 = 0x58;
+        pub const RETURN: u8 = 0xF3;
+        pub const REVERT: u8 = 0xFD;
+        pub const INVALID: u8 = 0xFE;
+        // EIP-4200 relative jumps and EIP-4750/6206 functions
+        pub const RJUMP: u8 = 0xE0;
+        pub const RJUMPI: u8 = 0xE1;
+        pub const RJUMPV: u8 = 0xE2;
+        pub const CALLF: u8 = 0xE3;
+        pub const RETF: u8 = 0xE4;
+        pub const JUMPF: u8 = 0xE5;
+    }
+
+    pub mod system {
+        pub const CREATE: u8 = 0xF0;
+        pub const CALLCODE: u8 = 0xF2;
+        pub const CREATE2: u8 = 0xF5;
+        pub const SELFDESTRUCT: u8 = 0xFF;
+    }
+
+    pub mod eof_specific {
+        // EIP-663
+        pub const DUPN: u8 = 0xE6;
+        pub const SWAPN: u8 = 0xE7;
+        pub const EXCHANGE: u8 = 0xE8;
+        // EIP-7480
+        pub const DATALOAD: u8 = 0xD0;
+        pub const DATALOADN: u8 = 0xD1;
+        pub const DATASIZE: u8 = 0xD2;
+        pub const DATACOPY: u8 = 0xD3;
+        // EIP-7620
+        pub const EOFCREATE: u8 = 0xEC;
+        pub const RETURNCONTRACT: u8 = 0xEE;
+    }
+}
+
+pub use opcodes::arithmetic::*;
+pub use opcodes::control_flow::*;
+pub use opcodes::environment::*;
+pub use opcodes::eof_specific::*;
+pub use opcodes::memory::*;
+pub use opcodes::push::*;
+pub use opcodes::stack::*;
+pub use opcodes::system::*;
+
+/// Static metadata for an opcode valid in EOF code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OpcodeInfo {
+    pub name: &'static str,
+    pub inputs: u8,
+    pub outputs: u8,
+    /// Fixed immediate size in bytes. For RJUMPV this covers only the `max_index`
+    /// byte; the jump table that follows is sized by `max_index`.
+    pub immediate_size: u8,
+    /// Whether the instruction ends its basic block without falling through.
+    pub terminating: bool,
+}
 
-// --- Opcodes for validation ---
-pub const JUMP: u8 = 0x56;
-pub const JUMPI: u8 = 0x57;
-pub const PC: u8 = 0x58;
-pub const INVALID: u8 = 0xFE;
-pub const SELFDESTRUCT: u8 = 0xFF;
-pub const PUSH1: u8 = 0x60;
-pub const PUSH2: u8 = 0x61;
-pub const PUSH32: u8 = 0x7F;
-// --- End Opcodes ---
+const fn op(name: &'static str, inputs: u8, outputs: u8, immediate_size: u8, terminating: bool) -> Option<OpcodeInfo> {
+    Some(OpcodeInfo { name, inputs, outputs, immediate_size, terminating })
+}
 
-// --- New Opcodes for Instruction Set Expansion ---
-pub const RJUMP: u8 = 0xE0;
-pub const RJUMPI: u8 = 0xE1;
-// --- End New Opcodes ---
+/// Returns the metadata for `opcode`, or `None` if it is undefined or removed in EOF.
+/// Stack effects for CALLF, JUMPF, DUPN, SWAPN and EXCHANGE depend on their
+/// immediates (or the callee's Type entry) and are listed here as their static minimum.
+pub fn opcode_info(opcode: u8) -> Option<OpcodeInfo> {
+    match opcode {
+        STOP => op("STOP", 0, 0, 0, true),
+        ADD => op("ADD", 2, 1, 0, false),
+        MUL => op("MUL", 2, 1, 0, false),
+        0x03 => op("SUB", 2, 1, 0, false),
+        0x04 => op("DIV", 2, 1, 0, false),
+        0x05 => op("SDIV", 2, 1, 0, false),
+        0x06 => op("MOD", 2, 1, 0, false),
+        0x07 => op("SMOD", 2, 1, 0, false),
+        0x08 => op("ADDMOD", 3, 1, 0, false),
+        0x09 => op("MULMOD", 3, 1, 0, false),
+        0x0A => op("EXP", 2, 1, 0, false),
+        0x0B => op("SIGNEXTEND", 2, 1, 0, false),
+        0x10 => op("LT", 2, 1, 0, false),
+        0x11 => op("GT", 2, 1, 0, false),
+        0x12 => op("SLT", 2, 1, 0, false),
+        0x13 => op("SGT", 2, 1, 0, false),
+        0x14 => op("EQ", 2, 1, 0, false),
+        0x15 => op("ISZERO", 1, 1, 0, false),
+        0x16 => op("AND", 2, 1, 0, false),
+        0x17 => op("OR", 2, 1, 0, false),
+        0x18 => op("XOR", 2, 1, 0, false),
+        0x19 => op("NOT", 1, 1, 0, false),
+        0x1A => op("BYTE", 2, 1, 0, false),
+        0x1B => op("SHL", 2, 1, 0, false),
+        0x1C => op("SHR", 2, 1, 0, false),
+        0x1D => op("SAR", 2, 1, 0, false),
+        0x20 => op("KECCAK256", 2, 1, 0, false),
+        0x30 => op("ADDRESS", 0, 1, 0, false),
+        0x31 => op("BALANCE", 1, 1, 0, false),
+        0x32 => op("ORIGIN", 0, 1, 0, false),
+        0x33 => op("CALLER", 0, 1, 0, false),
+        0x34 => op("CALLVALUE", 0, 1, 0, false),
+        0x35 => op("CALLDATALOAD", 1, 1, 0, false),
+        0x36 => op("CALLDATASIZE", 0, 1, 0, false),
+        0x37 => op("CALLDATACOPY", 3, 0, 0, false),
+        0x3A => op("GASPRICE", 0, 1, 0, false),
+        0x3D => op("RETURNDATASIZE", 0, 1, 0, false),
+        0x3E => op("RETURNDATACOPY", 3, 0, 0, false),
+        0x40 => op("BLOCKHASH", 1, 1, 0, false),
+        0x41 => op("COINBASE", 0, 1, 0, false),
+        0x42 => op("TIMESTAMP", 0, 1, 0, false),
+        0x43 => op("NUMBER", 0, 1, 0, false),
+        0x44 => op("PREVRANDAO", 0, 1, 0, false),
+        0x45 => op("GASLIMIT", 0, 1, 0, false),
+        0x46 => op("CHAINID", 0, 1, 0, false),
+        0x47 => op("SELFBALANCE", 0, 1, 0, false),
+        0x48 => op("BASEFEE", 0, 1, 0, false),
+        0x49 => op("BLOBHASH", 1, 1, 0, false),
+        0x4A => op("BLOBBASEFEE", 0, 1, 0, false),
+        POP => op("POP", 1, 0, 0, false),
+        MLOAD => op("MLOAD", 1, 1, 0, false),
+        MSTORE => op("MSTORE", 2, 0, 0, false),
+        MSTORE8 => op("MSTORE8", 2, 0, 0, false),
+        0x54 => op("SLOAD", 1, 1, 0, false),
+        0x55 => op("SSTORE", 2, 0, 0, false),
+        MSIZE => op("MSIZE", 0, 1, 0, false),
+        GAS => op("GAS", 0, 1, 0, false), // Retained in EOF, unlike the code introspection opcodes
+        0x5B => op("NOP", 0, 0, 0, false), // Legacy JUMPDEST
+        0x5C => op("TLOAD", 1, 1, 0, false),
+        0x5D => op("TSTORE", 2, 0, 0, false),
+        0x5E => op("MCOPY", 3, 0, 0, false),
+        PUSH0 => op("PUSH0", 0, 1, 0, false),
+        PUSH1..=PUSH32 => op("PUSH", 0, 1, opcode - PUSH1 + 1, false),
+        0x80..=0x8F => op("DUP", opcode - 0x80 + 1, opcode - 0x80 + 2, 0, false),
+        0x90..=0x9F => op("SWAP", opcode - 0x90 + 2, opcode - 0x90 + 2, 0, false),
+        0xA0..=0xA4 => op("LOG", opcode - 0xA0 + 2, 0, 0, false),
+        DATALOAD => op("DATALOAD", 1, 1, 0, false),
+        DATALOADN => op("DATALOADN", 0, 1, 2, false),
+        DATASIZE => op("DATASIZE", 0, 1, 0, false),
+        DATACOPY => op("DATACOPY", 3, 0, 0, false),
+        RJUMP => op("RJUMP", 0, 0, 2, true),
+        RJUMPI => op("RJUMPI", 1, 0, 2, false),
+        RJUMPV => op("RJUMPV", 1, 0, 1, false),
+        CALLF => op("CALLF", 0, 0, 2, false),
+        RETF => op("RETF", 0, 0, 0, true),
+        JUMPF => op("JUMPF", 0, 0, 2, true),
+        DUPN => op("DUPN", 1, 2, 1, false),
+        SWAPN => op("SWAPN", 2, 2, 1, false),
+        EXCHANGE => op("EXCHANGE", 3, 3, 1, false),
+        EOFCREATE => op("EOFCREATE", 4, 1, 1, false),
+        RETURNCONTRACT => op("RETURNCONTRACT", 2, 0, 1, true),
+        RETURN => op("RETURN", 2, 0, 0, true),
+        0xF7 => op("RETURNDATALOAD", 1, 1, 0, false),
+        0xF8 => op("EXTCALL", 4, 1, 0, false),
+        0xF9 => op("EXTDELEGATECALL", 3, 1, 0, false),
+        0xFB => op("EXTSTATICCALL", 3, 1, 0, false),
+        REVERT => op("REVERT", 2, 0, 0, true),
+        INVALID => op("INVALID", 0, 0, 0, true),
+        _ => None,
+    }
+}
 
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -60,93 +250,716 @@ pub struct EOFHeader {
     pub section_headers: Vec<SectionHeader>,
 }
 
+impl EOFHeader {
+    /// Returns the encoded header length in bytes, i.e. the offset at which the
+    /// first section body begins:
+    /// 2 (magic) + 1 (version) + 3 per section header (kind + u16 size) + 1 (terminator).
+    pub fn encoded_len(&self) -> usize {
+        3 + 3 * self.section_headers.len() + 1
+    }
+}
+
+/// EIP-4750: `outputs` value marking a non-returning function (EIP-6206).
+pub const NON_RETURNING_FUNCTION: u8 = 0x80;
+
+/// A decoded 4-byte Type section entry describing one code section.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FunctionType {
+    pub inputs: u8,
+    pub outputs: u8, // NON_RETURNING_FUNCTION for functions that never RETF
+    pub max_stack_height: u16,
+}
+
+impl FunctionType {
+    /// Decodes a Type section entry: inputs (u8), outputs (u8), max_stack_height (u16 BE).
+    pub fn from_bytes(entry: [u8; 4]) -> Self {
+        FunctionType {
+            inputs: entry[0],
+            outputs: entry[1],
+            max_stack_height: u16::from_be_bytes([entry[2], entry[3]]),
+        }
+    }
+
+    /// Encodes this entry back into its 4-byte Type section form.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        let [hi, lo] = self.max_stack_height.to_be_bytes();
+        [self.inputs, self.outputs, hi, lo]
+    }
+
+    pub fn is_non_returning(&self) -> bool {
+        self.outputs == NON_RETURNING_FUNCTION
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct EOFContainer {
     pub header: EOFHeader,
     pub sections: Vec<Vec<u8>>, // Raw bytes for each section
 }
 
+impl EOFContainer {
+    /// Returns the bodies of all Code sections, in header order.
+    pub fn code_sections(&self) -> impl Iterator<Item = &[u8]> {
+        self.header.section_headers.iter()
+            .zip(&self.sections)
+            .filter(|(header, _)| header.kind == SectionKind::Code)
+            .map(|(_, body)| body.as_slice())
+    }
+
+    /// Returns the body of the Type section, if the container has one.
+    pub fn type_section(&self) -> Option<&[u8]> {
+        self.section_of_kind(SectionKind::Type)
+    }
+
+    /// Returns the body of the Data section, if the container has one. The body
+    /// may be shorter than declared (see `data_padding`).
+    pub fn data_section(&self) -> Option<&[u8]> {
+        self.section_of_kind(SectionKind::Data)
+    }
+
+    /// Returns the body paired with the first header of `kind`.
+    fn section_of_kind(&self, kind: SectionKind) -> Option<&[u8]> {
+        self.header.section_headers.iter()
+            .zip(&self.sections)
+            .find(|(header, _)| header.kind == kind)
+            .map(|(_, body)| body.as_slice())
+    }
+
+    /// Returns the body of code section `index`.
+    fn code_section(&self, index: usize) -> Result<&[u8], EOFError> {
+        self.code_sections().nth(index).ok_or(EOFError::InvalidSectionIndex(index))
+    }
+
+    /// Returns the offset in code section 0 at which execution begins. EOF v1 always
+    /// starts at 0; this leaves room for a future declared entry offset.
+    pub fn entry_pc(&self) -> usize {
+        0
+    }
+
+    /// Returns the number of instructions (not bytes) in code section `code_section`.
+    pub fn instruction_count(&self, code_section: usize) -> Result<usize, EOFError> {
+        instructions(self.code_section(code_section)?).try_fold(0, |count, instr| instr.map(|_| count + 1))
+    }
+
+    /// Maps a byte `offset` in code section `code_section` to the ordinal of the
+    /// instruction starting there. Offsets inside immediate data are rejected.
+    pub fn offset_to_index(&self, code_section: usize, offset: usize) -> Result<usize, EOFError> {
+        let code = self.code_section(code_section)?;
+        for (index, instr) in instructions(code).enumerate() {
+            let instr = instr?;
+            if instr.offset == offset {
+                return Ok(index);
+            }
+            if instr.offset > offset {
+                break;
+            }
+        }
+        if offset < code.len() {
+            Err(EOFError::JumpToImmediate(offset))
+        } else {
+            Err(EOFError::UnexpectedEndOfInput)
+        }
+    }
+
+    /// Decodes the Type section body into one FunctionType per entry.
+    pub fn function_types(&self) -> Result<Vec<FunctionType>, EOFError> {
+        let body = self.type_section().ok_or(EOFError::MissingTerminator)?; // EIP-3540: Must have a Type section
+        if body.len() % 4 != 0 {
+            return Err(EOFError::TypeSectionSizeNotMultipleOf4(body.len() as u16)); // Type entries are 4 bytes each
+        }
+        Ok(body.chunks_exact(4)
+            .map(|entry| FunctionType::from_bytes([entry[0], entry[1], entry[2], entry[3]]))
+            .collect())
+    }
+
+    /// Returns every pair `(i, j)` with `i < j` of code sections whose bodies are
+    /// byte-identical, as merge candidates for an optimizer. Analysis only.
+    pub fn duplicate_code_sections(&self) -> Vec<(usize, usize)> {
+        let sections: Vec<&[u8]> = self.code_sections().collect();
+        let mut pairs = Vec::new();
+        for (i, a) in sections.iter().enumerate() {
+            for (j, b) in sections.iter().enumerate().skip(i + 1) {
+                if a == b {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Wraps `code` in a minimal container: one Type entry (no inputs, non-returning
+    /// as required of the entry function) whose max stack height is computed by
+    /// `validate_stack_heights`, one Code section and no Data section.
+    pub fn single_function(code: Vec<u8>) -> Result<EOFContainer, EOFError> {
+        let code_size = u16::try_from(code.len()).map_err(|_| EOFError::SectionTooLarge(code.len()))?;
+        let mut function = FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 };
+        function.max_stack_height = validate_stack_heights(&code, &[function], 0)?;
+        Ok(EOFContainer {
+            header: EOFHeader {
+                version: EOF_VERSION,
+                section_headers: vec![
+                    SectionHeader { kind: SectionKind::Type, size: 4 },
+                    SectionHeader { kind: SectionKind::Code, size: code_size },
+                ],
+            },
+            sections: vec![function.to_bytes().to_vec(), code],
+        })
+    }
+
+    /// Parses the body of the `index`th Container section into an owned container.
+    pub fn subcontainer(&self, index: usize) -> Result<EOFContainer, EOFError> {
+        let body = self.header.section_headers.iter()
+            .zip(&self.sections)
+            .filter(|(header, _)| header.kind == SectionKind::Container)
+            .nth(index)
+            .map(|(_, body)| body)
+            .ok_or(EOFError::InvalidSectionIndex(index))?;
+        parse_eof_container(body)
+    }
+
+    /// Splits code section `section` into basic blocks (see `build_cfg`), marking each
+    /// block reachable from the section entry by following jumps and fall-through.
+    pub fn basic_blocks(&self, section: usize) -> Result<Vec<BasicBlock>, EOFError> {
+        let cfg = build_cfg(self.code_section(section)?)?;
+        let mut reachable = vec![false; cfg.blocks.len()];
+        let mut worklist = if cfg.blocks.is_empty() { vec![] } else { vec![0] };
+        while let Some(block) = worklist.pop() {
+            if std::mem::replace(&mut reachable[block], true) {
+                continue;
+            }
+            worklist.extend(cfg.edges.iter().filter(|&&(from, _)| from == block).map(|&(_, to)| to));
+        }
+        Ok(cfg.blocks.iter().enumerate()
+            .map(|(index, &(start, end))| BasicBlock {
+                start,
+                end,
+                reachable: reachable[index],
+                successors: cfg.edges.iter().filter(|&&(from, _)| from == index).map(|&(_, to)| to).collect(),
+            })
+            .collect())
+    }
+
+    /// Returns the header and body of section `idx`, or `None` if either is missing
+    /// (e.g. a hand-built container whose header and section lists differ in length).
+    pub fn section(&self, idx: usize) -> Option<(&SectionHeader, &[u8])> {
+        Some((self.header.section_headers.get(idx)?, self.sections.get(idx)?.as_slice()))
+    }
+
+    /// Returns the declared `max_stack_height` of each function, in code section order.
+    pub fn max_stack_heights(&self) -> Result<Vec<u16>, EOFError> {
+        Ok(self.function_types()?.iter().map(|ft| ft.max_stack_height).collect())
+    }
+
+    /// Returns true if this container is initcode: some code section contains
+    /// RETURNCONTRACT, or EOFCREATE while the container carries subcontainers.
+    pub fn is_initcode(&self) -> bool {
+        let has_subcontainers = self.header.section_headers.iter().any(|h| h.kind == SectionKind::Container);
+        self.code_sections().any(|code| {
+            instructions(code).map_while(Result::ok).any(|instr| {
+                instr.opcode == RETURNCONTRACT || (instr.opcode == EOFCREATE && has_subcontainers)
+            })
+        })
+    }
+
+    /// Returns true if any code section uses an opcode that exists only in EOF
+    /// (relative jumps, functions, EIP-663 stack ops, data and container opcodes),
+    /// i.e. the code is more than legacy bytecode wrapped in a container.
+    pub fn uses_eof_features(&self) -> bool {
+        self.code_sections()
+            .flat_map(|code| instructions(code).map_while(Result::ok))
+            .any(|instr| matches!(
+                instr.opcode,
+                RJUMP | RJUMPI | RJUMPV | CALLF | RETF | JUMPF | DUPN | SWAPN | EXCHANGE
+                    | DATALOAD | DATALOADN | DATASIZE | DATACOPY | EOFCREATE | RETURNCONTRACT
+            ))
+    }
+
+    /// Fixes the RJUMP/RJUMPI/RJUMPV offsets of code section `section` after
+    /// `inserted_len` bytes were inserted at offset `at` (the body must already
+    /// contain them). Targets at or after `at` move with the shifted code, so a
+    /// jump to the old instruction at `at` still reaches it. Jumps inside the
+    /// inserted bytes are left alone. Fails with `InvalidJumpDestination` if an
+    /// adjusted offset no longer fits in 16 bits.
+    pub fn rewrite_offsets_after_insert(&mut self, section: usize, at: usize, inserted_len: usize) -> Result<(), EOFError> {
+        let position = self.header.section_headers.iter()
+            .enumerate()
+            .filter(|(_, header)| header.kind == SectionKind::Code)
+            .nth(section)
+            .map(|(position, _)| position)
+            .ok_or(EOFError::InvalidSectionIndex(section))?;
+        let code = self.sections.get_mut(position).ok_or(EOFError::MalformedSectionHeader)?;
+        if at.checked_add(inserted_len).is_none_or(|end| end > code.len()) {
+            return Err(EOFError::UnexpectedEndOfInput);
+        }
+        let jumps: Vec<(usize, usize)> = instructions(code)
+            .map_while(Result::ok)
+            .filter(|instr| matches!(instr.opcode, RJUMP | RJUMPI | RJUMPV))
+            .filter(|instr| instr.offset < at || instr.offset >= at + inserted_len)
+            .map(|instr| (instr.offset, 1 + instr.immediate.len()))
+            .collect();
+        for (pc, len) in jumps {
+            let old_next = if pc < at { pc + len } else { pc - inserted_len + len };
+            let offset_positions: Vec<usize> = match code[pc] {
+                RJUMPV => (0..=code[pc + 1] as usize).map(|entry| pc + 2 + entry * 2).collect(),
+                _ => vec![pc + 1],
+            };
+            for offset_pos in offset_positions {
+                let offset = i16::from_be_bytes([code[offset_pos], code[offset_pos + 1]]);
+                let old_target = old_next as isize + offset as isize;
+                let new_target = if old_target >= at as isize { old_target + inserted_len as isize } else { old_target };
+                let new_offset = i16::try_from(new_target - (pc + len) as isize).map_err(|_| EOFError::InvalidJumpDestination(pc))?;
+                code[offset_pos..offset_pos + 2].copy_from_slice(&new_offset.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the container back into EOF bytecode, using the declared header sizes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.header.encoded_len() + self.sections.iter().map(Vec::len).sum::<usize>());
+        bytes.extend_from_slice(&EOF_MAGIC.to_be_bytes());
+        bytes.push(self.header.version);
+        for header in &self.header.section_headers {
+            bytes.push(header.kind as u8);
+            bytes.extend_from_slice(&header.size.to_be_bytes());
+        }
+        bytes.push(0x00); // Terminator
+        for section in &self.sections {
+            bytes.extend_from_slice(section);
+        }
+        bytes
+    }
+
+    /// Lists every EOFCREATE and RETURNCONTRACT as
+    /// `(code section index, byte offset, referenced container index)`.
+    pub fn subcontainer_references(&self) -> Vec<(usize, usize, u8)> {
+        self.code_sections().enumerate()
+            .flat_map(|(section, code)| {
+                instructions(code).map_while(Result::ok)
+                    .filter(|instr| instr.opcode == EOFCREATE || instr.opcode == RETURNCONTRACT)
+                    .map(move |instr| (section, instr.offset, instr.immediate[0]))
+            })
+            .collect()
+    }
+
+    /// Returns a copy of this container with the body of code section `index`
+    /// replaced by `new_code` and its header size updated to match.
+    pub fn replace_code_section(&self, index: usize, new_code: Vec<u8>) -> Result<EOFContainer, EOFError> {
+        let size = u16::try_from(new_code.len()).map_err(|_| EOFError::SectionTooLarge(new_code.len()))?;
+        let position = self.header.section_headers.iter()
+            .enumerate()
+            .filter(|(_, header)| header.kind == SectionKind::Code)
+            .nth(index)
+            .map(|(position, _)| position)
+            .ok_or(EOFError::InvalidSectionIndex(index))?;
+        let mut container = self.clone();
+        *container.sections.get_mut(position).ok_or(EOFError::MalformedSectionHeader)? = new_code;
+        container.header.section_headers[position].size = size;
+        Ok(container)
+    }
+
+    /// Appends a code section described by `ft`, adding its Type entry and growing
+    /// the Type header in the same step so the two never fall out of sync. The new
+    /// Code section goes after the existing ones, ahead of any Container/Data sections.
+    pub fn push_code(&mut self, code: Vec<u8>, ft: FunctionType) -> Result<(), EOFError> {
+        // Headers and bodies are inserted side by side, so the two lists must line up
+        if self.header.section_headers.len() != self.sections.len() {
+            return Err(EOFError::MalformedSectionHeader);
+        }
+        let code_size = u16::try_from(code.len()).map_err(|_| EOFError::SectionTooLarge(code.len()))?;
+        let type_position = self.header.section_headers.iter()
+            .position(|header| header.kind == SectionKind::Type)
+            .ok_or(EOFError::MissingTerminator)?;
+        let type_size = self.header.section_headers[type_position].size as usize + 4;
+        let type_size = u16::try_from(type_size).map_err(|_| EOFError::SectionTooLarge(type_size))?;
+        let position = self.header.section_headers.iter()
+            .rposition(|header| header.kind == SectionKind::Code)
+            .unwrap_or(type_position) + 1;
+
+        self.header.section_headers[type_position].size = type_size;
+        self.sections[type_position].extend_from_slice(&ft.to_bytes());
+        self.header.section_headers.insert(position, SectionHeader { kind: SectionKind::Code, size: code_size });
+        self.sections.insert(position, code);
+        Ok(())
+    }
+
+    /// Returns how many zero bytes the runtime pads the Data section with: the
+    /// declared size minus the bytes actually present (0 without deferred data).
+    pub fn data_padding(&self) -> usize {
+        self.header.section_headers.iter()
+            .zip(&self.sections)
+            .find(|(header, _)| header.kind == SectionKind::Data)
+            .map_or(0, |(header, body)| (header.size as usize).saturating_sub(body.len()))
+    }
+
+    /// Returns a copy of this container with the Data section (header and body) removed.
+    /// Useful for comparing two containers that differ only in appended data.
+    pub fn without_data(&self) -> EOFContainer {
+        let (section_headers, sections) = self.header.section_headers.iter()
+            .zip(&self.sections)
+            .filter(|(header, _)| header.kind != SectionKind::Data)
+            .map(|(header, body)| (header.clone(), body.clone()))
+            .unzip();
+        EOFContainer {
+            header: EOFHeader { version: self.header.version, section_headers },
+            sections,
+        }
+    }
+
+    /// Compares two containers, ignoring Data bytes beyond the shorter of the two
+    /// present Data bodies (e.g. a deployed container versus its appended variant).
+    pub fn eq_ignoring_appended_data(&self, other: &Self) -> bool {
+        let (a, b) = (self.data_section().unwrap_or_default(), other.data_section().unwrap_or_default());
+        let common = a.len().min(b.len());
+        self.without_data() == other.without_data() && a[..common] == b[..common]
+    }
+
+    /// Keccak-256 over the serialized Type, Code and Container sections (headers included),
+    /// ignoring the Data section. Containers that differ only in data share this hash.
+    #[cfg(feature = "keccak")]
+    pub fn code_identity_hash(&self) -> [u8; 32] {
+        use tiny_keccak::{Hasher, Keccak};
+        let mut hasher = Keccak::v256();
+        hasher.update(&self.without_data().to_bytes());
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+        hash
+    }
+}
+
+/// Borrowed view of an EOF container whose section bodies are slices of the
+/// parsed bytecode, avoiding a copy per section. See `parse_eof_container_ref`.
 #[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EOFContainerRef<'a> {
+    pub header: EOFHeader,
+    pub sections: Vec<&'a [u8]>,
+}
+
+impl EOFContainerRef<'_> {
+    /// Copies the section bodies into an owned `EOFContainer`.
+    pub fn to_owned(&self) -> EOFContainer {
+        EOFContainer {
+            header: self.header.clone(),
+            sections: self.sections.iter().map(|body| body.to_vec()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Error)]
 pub enum EOFError {
-    InvalidMagic,
+    #[error("Not an EOF container (first byte is not 0xEF)")]
+    NotEOF,
+    #[error("Invalid EOF magic number")]
+    InvalidMagic, // First byte is 0xEF but the second is not 0x00
+    #[error("Invalid EOF version: {0}")]
     InvalidVersion(u8),
+    #[error("Missing EOF section terminator (0x00)")]
     MissingTerminator,
+    #[error("EOF container has no code section")]
+    MissingCodeSection, // Container declares no Code section
+    #[error("Unexpected end of input during parsing")]
     UnexpectedEndOfInput,
+    #[error("Invalid section kind: {0}")]
     InvalidSectionKind(u8),
+    #[error("Declared section size does not match actual content size")]
     SectionSizeMismatch,
+    #[error("Too many sections of a certain kind")]
     TooManySections, // EIP-3540 limits (max 256 for code, 1 for data etc.)
-    DuplicateSection(SectionKind),
+    #[error("Duplicate section kind: {kind:?} at header index {at_index}")]
+    DuplicateSection { kind: SectionKind, at_index: usize }, // Index of the repeated section header
+    #[error("Malformed section header")]
     MalformedSectionHeader,
+    #[error("Type section size {0} is not a multiple of 4")]
+    TypeSectionSizeNotMultipleOf4(u16), // Type section present, but not made of whole 4-byte entries
+    #[error("Unsupported section kind: {0}")]
     UnsupportedSectionKind(u8), // New error for unhandled section kinds
+    #[error("Total declared section size exceeds {max} bytes", max = MAX_CONTAINER_SIZE)]
+    ContainerTooLarge, // Total declared section size exceeds MAX_CONTAINER_SIZE
+    #[error("Initcode container of {0} bytes exceeds {max} bytes", max = MAX_INITCODE_SIZE)]
+    InitcodeTooLarge(usize), // Encoded size of an initcode container exceeds MAX_INITCODE_SIZE
+    #[error("Runtime container of {0} bytes exceeds {max} bytes", max = MAX_CODE_SIZE)]
+    RuntimeCodeTooLarge(usize), // Encoded size of a RETURNCONTRACT target exceeds MAX_CODE_SIZE
+    #[error("Section of {0} bytes exceeds the u16 size limit")]
+    SectionTooLarge(usize), // Section body does not fit the u16 header size field
     // EIP-3670 Validation Errors
+    #[error("Code section contains invalid opcode: 0x{0:02x}")]
     InvalidOpcode(u8),
+    #[error("Code section contains truncated PUSH data")]
     TruncatedPushData,
+    #[error("Forbidden JUMPDEST related opcode in EOF: 0x{0:02x}")]
     JumpDestForbidden(u8), // e.g. JUMP/JUMPI/PC
+    #[error("Simulated stack underflow")]
     StackUnderflow,
+    #[error("Simulated stack overflow")]
     StackOverflow,
+    #[error("Code section contains truncated immediate for opcode 0x{0:02x}")]
+    TruncatedImmediate(u8), // Opcode whose immediate runs past the end of the code section
+    #[error("Opcode removed in EOF: 0x{0:02x}")]
+    OpcodeRemovedInEOF(u8), // Code/account introspection opcodes removed by EOF
+    #[error("Opcode disabled by validation config: 0x{0:02x}")]
+    OpcodeDisabledByConfig(u8), // Opcode valid in EOF but disabled by ValidationConfig
+    #[error("Opcode not in the configured allow-list: 0x{0:02x}")]
+    OpcodeNotAllowed(u8), // Opcode missing from ValidationConfig::allowed_opcodes
+    // EIP-4200 Validation Errors
+    #[error("Relative jump at offset {0} targets outside its code section (jumps cannot cross sections)")]
+    InvalidJumpDestination(usize), // Relative jump at this offset targets outside its code section
+    #[error("Offset {0} is not an instruction boundary")]
+    JumpToImmediate(usize), // Offset lies inside an instruction's immediate data
+    // EIP-5450 Validation Errors
+    #[error("Paths reaching offset {0} imply different stack heights")]
+    StackHeightMismatch(usize), // Join point reached with differing stack heights
+    #[error("Offset {0} is not reachable from the section entry")]
+    UnreachableOffset(usize), // Offset in dead code, which has no computed stack height
+    #[error("Declared max_stack_height {declared} differs from computed maximum {computed}")]
+    DeclaredMaxStackMismatch { declared: u16, computed: u16 }, // Type entry max_stack_height differs from the analyzed maximum
+    #[error("Type entry {type_index} does not match its code section")]
+    TypeCodeMismatch { type_index: usize }, // Type entry cannot describe its code section
+    // EIP-6206 Validation Errors
+    #[error("CALLF targets non-returning function {0}")]
+    CallfToNonReturning(u16), // CALLF targets a non-returning function (reachable only via JUMPF)
+    #[error("JUMPF to function {0} whose outputs differ from the current function")]
+    JumpfOutputMismatch(u16), // JUMPF target returns a different number of outputs than the current function
+    // EIP-7480 Validation Errors
+    #[error("DATALOADN offset {0} reads past the declared data section size")]
+    DataloadnOutOfBounds(u16), // DATALOADN offset + 32 exceeds the declared data size
+    #[error("DATACOPY at offset {offset} of code section {section} reads past the declared data section size")]
+    DataCopyOutOfBounds { section: usize, offset: usize }, // Constant-argument DATACOPY reads past the declared data size
+    #[error("Data section is never read by DATALOAD, DATALOADN, DATASIZE or DATACOPY")]
+    UnusedDataSection, // Non-empty Data section never read by a data opcode
+    // EIP-7620 Validation Errors
+    #[error("Legacy contract creation opcode forbidden in EOF: 0x{0:02x}")]
+    LegacyCreateForbidden(u8), // CREATE/CREATE2 are replaced by EOFCREATE
+    #[error("RETURNCONTRACT is not allowed in runtime code")]
+    ReturnContractInRuntime, // Runtime (RETURNCONTRACT-deployed) container contains RETURNCONTRACT
+    #[error("Opcode 0x{0:02x} is not allowed in this container context (initcode vs runtime)")]
+    OpcodeWrongContext(u8), // Any other opcode whose `opcode_context` excludes the container kind
+    #[error("Subcontainer {0} returned by RETURNCONTRACT is not a valid EOF container")]
+    InvalidReturnedContainer(usize), // Subcontainer deployed by RETURNCONTRACT is not a parseable EOF container
+    #[error("EOFCREATE target container {0} is not initcode")]
+    EofCreateTargetNotInitcode(usize), // Container section index used by EOFCREATE holds runtime code
+    #[error("Subcontainer {0} is never referenced by EOFCREATE or RETURNCONTRACT")]
+    UnreferencedSubcontainer(usize), // Container section never referenced by EOFCREATE/RETURNCONTRACT
+    // API Errors
+    #[error("Section index {0} is out of range")]
+    InvalidSectionIndex(usize), // Referenced section index does not exist
+    #[error("Expected {expected} functions but the container has {found}")]
+    FunctionCountMismatch { expected: usize, found: usize }, // Code section count differs from what the caller expects
+    #[error("EOF container not allowed where legacy bytecode is expected")]
+    UnexpectedEof, // EOF container supplied where only legacy bytecode is allowed
+    #[error("I/O error: {0}")]
+    Io(String), // Reading the input failed; holds the io::Error message
+    #[error("Input is not valid hex")]
+    InvalidHex, // Input text is not an even-length hex string
+    // Simulation Errors
+    #[error("Simulation exceeded the step limit without terminating")]
+    StepLimitExceeded, // simulate_eof_run hit max_steps without terminating
+    #[error("Simulated call stack exceeded {max} frames", max = MAX_CALL_DEPTH)]
+    CallStackOverflow, // CALLF exceeded MAX_CALL_DEPTH return frames
 }
 
-impl std::fmt::Display for EOFError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl TryFrom<&[u8]> for EOFContainer {
+    type Error = EOFError;
+    fn try_from(bytecode: &[u8]) -> Result<Self, Self::Error> {
+        parse_eof_container(bytecode)
+    }
+}
+
+impl From<EOFContainer> for Vec<u8> {
+    fn from(container: EOFContainer) -> Self {
+        container.to_bytes()
+    }
+}
+
+/// Coarse classification of an `EOFError`, e.g. for mapping to API response codes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorCategory {
+    Malformed,     // Encoding or structural problem in the container
+    InvalidOpcode, // Opcode undefined or not allowed in EOF code
+    StackError,    // Operand or call stack bounds violated
+    SizeLimit,     // Container, section or step limits exceeded
+    Unsupported,   // Valid-looking input this implementation does not accept
+}
+
+impl EOFError {
+    /// Stable numeric code of this error for CLI and log output, grouped by hundreds:
+    /// 1xx parsing, 3xx EIP-3670, 4xx EIP-4200, 5xx EIP-5450, 6xx EIP-6206,
+    /// 7xx EIP-7480, 8xx EIP-7620, 9xx API and simulation. Codes are never reused.
+    pub fn code(&self) -> u16 {
+        match self {
+            EOFError::NotEOF => 101,
+            EOFError::InvalidMagic => 102,
+            EOFError::InvalidVersion(_) => 103,
+            EOFError::MissingTerminator => 104,
+            EOFError::UnexpectedEndOfInput => 105,
+            EOFError::InvalidSectionKind(_) => 106,
+            EOFError::SectionSizeMismatch => 107,
+            EOFError::TooManySections => 108,
+            EOFError::DuplicateSection { .. } => 109,
+            EOFError::MalformedSectionHeader => 110,
+            EOFError::TypeSectionSizeNotMultipleOf4(_) => 111,
+            EOFError::UnsupportedSectionKind(_) => 112,
+            EOFError::ContainerTooLarge => 113,
+            EOFError::InitcodeTooLarge(_) => 114,
+            EOFError::SectionTooLarge(_) => 115,
+            EOFError::MissingCodeSection => 116,
+            EOFError::RuntimeCodeTooLarge(_) => 117,
+            EOFError::InvalidOpcode(_) => 301,
+            EOFError::TruncatedPushData => 302,
+            EOFError::JumpDestForbidden(_) => 303,
+            EOFError::StackUnderflow => 304,
+            EOFError::StackOverflow => 305,
+            EOFError::TruncatedImmediate(_) => 306,
+            EOFError::OpcodeRemovedInEOF(_) => 307,
+            EOFError::OpcodeDisabledByConfig(_) => 308,
+            EOFError::OpcodeNotAllowed(_) => 309,
+            EOFError::InvalidJumpDestination(_) => 401,
+            EOFError::JumpToImmediate(_) => 402,
+            EOFError::StackHeightMismatch(_) => 501,
+            EOFError::UnreachableOffset(_) => 502,
+            EOFError::DeclaredMaxStackMismatch { .. } => 503,
+            EOFError::TypeCodeMismatch { .. } => 504,
+            EOFError::CallfToNonReturning(_) => 601,
+            EOFError::JumpfOutputMismatch(_) => 602,
+            EOFError::DataloadnOutOfBounds(_) => 701,
+            EOFError::DataCopyOutOfBounds { .. } => 702,
+            EOFError::UnusedDataSection => 703,
+            EOFError::LegacyCreateForbidden(_) => 801,
+            EOFError::OpcodeWrongContext(_) => 802,
+            EOFError::InvalidReturnedContainer(_) => 803,
+            EOFError::EofCreateTargetNotInitcode(_) => 804,
+            EOFError::UnreferencedSubcontainer(_) => 805,
+            EOFError::ReturnContractInRuntime => 806,
+            EOFError::InvalidSectionIndex(_) => 901,
+            EOFError::FunctionCountMismatch { .. } => 902,
+            EOFError::UnexpectedEof => 903,
+            EOFError::Io(_) => 904,
+            EOFError::InvalidHex => 905,
+            EOFError::StepLimitExceeded => 951,
+            EOFError::CallStackOverflow => 952,
+        }
+    }
+
+    /// One-line `error[E<code>]: <message>` summary for grep-friendly CLI output.
+    pub fn summary(&self) -> String {
+        format!("error[E{:03}]: {}", self.code(), self)
+    }
+
+    pub fn category(&self) -> ErrorCategory {
         match self {
-            EOFError::InvalidMagic => write!(f, "Invalid EOF magic number"),
-            EOFError::InvalidVersion(v) => write!(f, "Invalid EOF version: {}", v),
-            EOFError::MissingTerminator => write!(f, "Missing EOF section terminator (0x00)"),
-            EOFError::UnexpectedEndOfInput => write!(f, "Unexpected end of input during parsing"),
-            EOFError::InvalidSectionKind(k) => write!(f, "Invalid section kind: {}", k),
-            EOFError::SectionSizeMismatch => write!(f, "Declared section size does not match actual content size"),
-            EOFError::TooManySections => write!(f, "Too many sections of a certain kind"),
-            EOFError::DuplicateSection(k) => write!(f, "Duplicate section kind: {:?}", k),
-            EOFError::MalformedSectionHeader => write!(f, "Malformed section header"),
-            EOFError::UnsupportedSectionKind(k) => write!(f, "Unsupported section kind: {}", k),
-            // EIP-3670 Validation Errors
-            EOFError::InvalidOpcode(op) => write!(f, "Code section contains invalid opcode: 0x{:02x}", op),
-            EOFError::TruncatedPushData => write!(f, "Code section contains truncated PUSH data"),
-            EOFError::JumpDestForbidden(op) => write!(f, "Forbidden JUMPDEST related opcode in EOF: 0x{:02x}", op),
-            EOFError::StackUnderflow => write!(f, "Simulated stack underflow"),
-            EOFError::StackOverflow => write!(f, "Simulated stack overflow"),
+            EOFError::NotEOF
+            | EOFError::InvalidMagic
+            | EOFError::MissingTerminator
+            | EOFError::UnexpectedEndOfInput
+            | EOFError::InvalidSectionKind(_)
+            | EOFError::SectionSizeMismatch
+            | EOFError::DuplicateSection { .. }
+            | EOFError::MalformedSectionHeader
+            | EOFError::TypeSectionSizeNotMultipleOf4(_)
+            | EOFError::TruncatedPushData
+            | EOFError::TruncatedImmediate(_)
+            | EOFError::InvalidJumpDestination(_)
+            | EOFError::JumpToImmediate(_)
+            | EOFError::CallfToNonReturning(_)
+            | EOFError::DataloadnOutOfBounds(_)
+            | EOFError::ReturnContractInRuntime
+            | EOFError::OpcodeWrongContext(_)
+            | EOFError::UnreferencedSubcontainer(_)
+            | EOFError::InvalidHex
+            | EOFError::UnusedDataSection
+            | EOFError::InvalidReturnedContainer(_)
+            | EOFError::FunctionCountMismatch { .. }
+            | EOFError::DataCopyOutOfBounds { .. }
+            | EOFError::UnreachableOffset(_)
+            | EOFError::EofCreateTargetNotInitcode(_)
+            | EOFError::MissingCodeSection
+            | EOFError::TypeCodeMismatch { .. }
+            | EOFError::InvalidSectionIndex(_) => ErrorCategory::Malformed,
+            EOFError::InvalidOpcode(_)
+            | EOFError::JumpDestForbidden(_)
+            | EOFError::OpcodeRemovedInEOF(_)
+            | EOFError::LegacyCreateForbidden(_) => ErrorCategory::InvalidOpcode,
+            EOFError::StackUnderflow
+            | EOFError::StackOverflow
+            | EOFError::StackHeightMismatch(_)
+            | EOFError::JumpfOutputMismatch(_)
+            | EOFError::DeclaredMaxStackMismatch { .. }
+            | EOFError::CallStackOverflow => ErrorCategory::StackError,
+            EOFError::TooManySections
+            | EOFError::ContainerTooLarge
+            | EOFError::SectionTooLarge(_)
+            | EOFError::InitcodeTooLarge(_)
+            | EOFError::RuntimeCodeTooLarge(_)
+            | EOFError::StepLimitExceeded => ErrorCategory::SizeLimit,
+            EOFError::InvalidVersion(_)
+            | EOFError::UnsupportedSectionKind(_)
+            | EOFError::OpcodeDisabledByConfig(_)
+            | EOFError::Io(_)
+            | EOFError::OpcodeNotAllowed(_)
+            | EOFError::UnexpectedEof => ErrorCategory::Unsupported,
         }
     }
 }
 
-impl std::error::Error for EOFError {}
+/// Reads the big-endian u16 at `offset`, as used for the magic, section sizes
+/// and 2-byte immediates. Fails with `UnexpectedEndOfInput` if it does not fit.
+fn read_u16_be(bytes: &[u8], offset: usize) -> Result<u16, EOFError> {
+    let end = offset.checked_add(2).ok_or(EOFError::UnexpectedEndOfInput)?;
+    match bytes.get(offset..end) {
+        Some(&[hi, lo]) => Ok(u16::from_be_bytes([hi, lo])),
+        _ => Err(EOFError::UnexpectedEndOfInput),
+    }
+}
 
-/// Parses a byte slice into an EOFContainer.
-pub fn parse_eof_container(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
+/// Parses the magic, version and section headers (through the 0x00 terminator),
+/// returning the header and the offset at which the section bodies begin.
+fn parse_header(bytecode: &[u8]) -> Result<(EOFHeader, usize), EOFError> {
+    parse_header_at(bytecode).map_err(|(err, _)| err)
+}
+
+/// Like `parse_header`, but reports the offset of the offending byte on failure.
+fn parse_header_at(bytecode: &[u8]) -> Result<(EOFHeader, usize), (EOFError, usize)> {
     let mut cursor = 0;
 
-    // 1. Check magic (0xEF00)
-    if bytecode.len() < 2 {
-        return Err(EOFError::UnexpectedEndOfInput);
+    // 1. Check magic (0xEF00): EIP-3540 treats 0xEF as the EOF prefix and the
+    // following 0x00 as a separate byte, so each is checked on its own.
+    let [prefix, second] = EOF_MAGIC.to_be_bytes();
+    match bytecode.first() {
+        None => return Err((EOFError::UnexpectedEndOfInput, 0)),
+        Some(&byte) if byte != prefix => return Err((EOFError::NotEOF, 0)),
+        Some(_) => {},
     }
-    let magic = u16::from_be_bytes(bytecode[0..2].try_into().unwrap());
-    if magic != EOF_MAGIC {
-        return Err(EOFError::InvalidMagic);
+    match bytecode.get(1) {
+        None => return Err((EOFError::UnexpectedEndOfInput, 1)),
+        Some(&byte) if byte != second => return Err((EOFError::InvalidMagic, 1)),
+        Some(_) => {},
     }
     cursor += 2;
 
     // 2. Check version (0x01)
     if bytecode.len() < cursor + 1 {
-        return Err(EOFError::UnexpectedEndOfInput);
+        return Err((EOFError::UnexpectedEndOfInput, cursor));
     }
     let version = bytecode[cursor];
     if version != EOF_VERSION {
-        return Err(EOFError::InvalidVersion(version));
+        return Err((EOFError::InvalidVersion(version), cursor));
     }
     cursor += 1;
 
     // 3. Parse section headers until 0x00 terminator
     let mut section_headers = Vec::new();
-    let mut seen_section_kinds: HashSet<SectionKind> = HashSet::new();
-    let mut code_section_count = 0;
     let mut type_section_count = 0;
     let mut data_section_count = 0;
 
 
     loop {
         if bytecode.len() < cursor + 1 {
-            return Err(EOFError::UnexpectedEndOfInput);
+            if !section_headers.is_empty() {
+                // Input ended after complete headers without the 0x00 terminator
+                return Err((EOFError::MissingTerminator, cursor));
+            }
+            return Err((EOFError::UnexpectedEndOfInput, cursor));
         }
+        let header_start = cursor;
         let kind_byte = bytecode[cursor];
         cursor += 1;
 
@@ -154,27 +967,21 @@ pub fn parse_eof_container(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
             break;
         }
 
-        let kind = SectionKind::try_from(kind_byte)?;
+        let kind = SectionKind::try_from(kind_byte).map_err(|err| (err, header_start))?;
 
-        if bytecode.len() < cursor + 2 {
-            return Err(EOFError::UnexpectedEndOfInput);
-        }
-        let size = u16::from_be_bytes(bytecode[cursor..cursor+2].try_into().unwrap());
+        let size = read_u16_be(bytecode, cursor).map_err(|err| (err, cursor))?;
         cursor += 2;
 
         match kind {
             SectionKind::Type => {
                 type_section_count += 1;
-                if type_section_count > 1 { return Err(EOFError::DuplicateSection(kind)); }
-            },
-            SectionKind::Code => {
-                code_section_count += 1;
+                if type_section_count > 1 { return Err((EOFError::DuplicateSection { kind, at_index: section_headers.len() }, header_start)); }
             },
             SectionKind::Data => {
                 data_section_count += 1;
-                if data_section_count > 1 { return Err(EOFError::DuplicateSection(kind)); }
+                if data_section_count > 1 { return Err((EOFError::DuplicateSection { kind, at_index: section_headers.len() }, header_start)); }
             },
-            _ => {} // Other section kinds can have duplicates
+            _ => {} // Code and Container sections can repeat
         }
 
         section_headers.push(SectionHeader { kind, size });
@@ -185,448 +992,3794 @@ pub fn parse_eof_container(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
     }
 
     if section_headers.is_empty() {
-        return Err(EOFError::MissingTerminator); // Should have at least one section before terminator
+        return Err((EOFError::MissingTerminator, cursor)); // Should have at least one section before terminator
     }
     if type_section_count == 0 {
-        return Err(EOFError::MissingTerminator); // EIP-3540: Must have a Type section
+        return Err((EOFError::MissingTerminator, cursor)); // EIP-3540: Must have a Type section
     }
 
+    Ok((EOFHeader { version, section_headers }, cursor))
+}
+
+/// Parses only the magic, version and section headers. Section bodies are neither
+/// extracted nor required to be present, which makes this cheap for indexing.
+pub fn parse_eof_header(bytecode: &[u8]) -> Result<EOFHeader, EOFError> {
+    parse_header(bytecode).map(|(header, _)| header)
+}
+
+/// Parses a byte slice into an EOFContainer.
+pub fn parse_eof_container(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
+    parse_eof_container_at(bytecode).map_err(|(err, _)| err)
+}
+
+/// Like `parse_eof_container`, but pairs a failure with the offset at which parsing
+/// stopped: the offending header byte, the end of input for a truncated body, or the
+/// first stray byte after the declared sections.
+pub fn parse_eof_container_at(bytecode: &[u8]) -> Result<EOFContainer, (EOFError, usize)> {
+    parse_container_ref_at(bytecode).map(|container| container.to_owned())
+}
+
+/// Like `parse_eof_container`, but borrows the section bodies from `bytecode`.
+pub fn parse_eof_container_ref(bytecode: &[u8]) -> Result<EOFContainerRef<'_>, EOFError> {
+    parse_container_ref_at(bytecode).map_err(|(err, _)| err)
+}
+
+fn parse_container_ref_at(bytecode: &[u8]) -> Result<EOFContainerRef<'_>, (EOFError, usize)> {
+    let (EOFHeader { version, section_headers }, mut cursor) = parse_header_at(bytecode)?;
 
-    // 4. Extract section contents
-    let mut sections = Vec::new();
-    let mut total_declared_size: usize = 0;
+    // 4. Extract section contents, bounding the aggregate declared size before
+    // allocating anything so untrusted headers cannot force large copies.
+    let total_declared_size: usize = section_headers.iter().map(|h| h.size as usize).sum();
+    if total_declared_size > MAX_CONTAINER_SIZE {
+        return Err((EOFError::ContainerTooLarge, cursor));
+    }
+    let mut sections = Vec::with_capacity(section_headers.len());
     for header in &section_headers {
-        total_declared_size = total_declared_size.checked_add(header.size as usize).ok_or(EOFError::SectionSizeMismatch)?;
+        if header.size == 0 {
+            // e.g. an empty deferred Data section: nothing to consume, cursor stays put
+            sections.push(&bytecode[cursor..cursor]);
+            continue;
+        }
         if bytecode.len() < cursor + header.size as usize {
-            return Err(EOFError::UnexpectedEndOfInput);
+            return Err((EOFError::UnexpectedEndOfInput, bytecode.len()));
         }
-        let section_content = bytecode[cursor..cursor + header.size as usize].to_vec();
-        sections.push(section_content);
+        sections.push(&bytecode[cursor..cursor + header.size as usize]);
         cursor += header.size as usize;
     }
 
     // 5. Check for stray bytes
     if bytecode.len() > cursor {
         // According to EIP-3540, no stray bytes are allowed after the declared sections.
-        return Err(EOFError::SectionSizeMismatch);
+        return Err((EOFError::SectionSizeMismatch, cursor));
     }
 
-    Ok(EOFContainer {
+    Ok(EOFContainerRef {
         header: EOFHeader { version, section_headers },
         sections,
     })
 }
 
-/// Validates an EOFContainer according to EIP-3670 and related EIPs.
-pub fn validate_eof_container(container: &EOFContainer) -> Result<(), EOFError> {
-    let mut code_section_count = 0;
-    let mut data_section_found = false;
-
-    let mut section_kind_order: Vec<SectionKind> = Vec::new();
+/// Reads a file holding hex-encoded bytecode (optionally `0x`-prefixed and
+/// surrounded by whitespace) and parses it as an EOF container.
+pub fn parse_eof_file<P: AsRef<Path>>(path: P) -> Result<EOFContainer, EOFError> {
+    let text = std::fs::read_to_string(path).map_err(|err| EOFError::Io(err.to_string()))?;
+    parse_eof_container(&decode_hex(&text)?)
+}
 
-    for (idx, header) in container.header.section_headers.iter().enumerate() {
-        // EIP-3540: Section order validation (Type, Code, Container, Data)
-        // Simplified for prototype: Type must be first, Data must be last if present.
-        match header.kind {
-            SectionKind::Type => {
-                if idx != 0 {
-                    return Err(EOFError::MalformedSectionHeader); // Type must be first
-                }
-                section_kind_order.push(header.kind);
-            },
-            SectionKind::Code => {
-                code_section_count += 1;
-                if section_kind_order.last() != Some(&SectionKind::Type) && section_kind_order.last() != Some(&SectionKind::Code) {
-                    // Code sections must follow Type section or other Code sections
-                    return Err(EOFError::MalformedSectionHeader);
-                }
-                section_kind_order.push(header.kind);
-            },
-            SectionKind::Data => {
-                data_section_found = true;
-                if section_kind_order.contains(&SectionKind::Container) {
-                    return Err(EOFError::MalformedSectionHeader); // Data must not be followed by Container
-                }
-                section_kind_order.push(header.kind);
-            },
-            SectionKind::Container => {
-                if data_section_found {
-                    return Err(EOFError::MalformedSectionHeader); // Container must not follow Data
-                }
-                section_kind_order.push(header.kind);
-            },
-        }
+/// Decodes hex text, ignoring surrounding whitespace and an optional `0x` prefix.
+fn decode_hex(text: &str) -> Result<Vec<u8>, EOFError> {
+    let hex = text.trim();
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if !hex.len().is_multiple_of(2) {
+        return Err(EOFError::InvalidHex);
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()).ok_or(EOFError::InvalidHex))
+        .collect()
+}
 
-        if header.size == 0 && (header.kind == SectionKind::Type || header.kind == SectionKind::Code) {
-            return Err(EOFError::SectionSizeMismatch); // Type and Code sections cannot be empty
+/// Like `parse_eof_container`, but additionally checks that no instruction's
+/// immediate (e.g. PUSH data) runs past the end of its code section, so the
+/// returned container can be walked safely without full validation.
+pub fn parse_eof_container_strict(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
+    let container = parse_eof_container(bytecode)?;
+    for code in container.code_sections() {
+        for instr in instructions(code) {
+            match instr {
+                Err(EOFError::TruncatedImmediate(PUSH1..=PUSH32)) => return Err(EOFError::TruncatedPushData),
+                Err(err) => return Err(err),
+                Ok(_) => {},
+            }
         }
     }
+    Ok(container)
+}
 
-    if code_section_count == 0 {
-        return Err(EOFError::MissingTerminator); // EIP-3540: Must have at least one code section
-    }
+/// A fully structured, validated view of an EOF container with each header
+/// already correlated with its body.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DecodedEOF {
+    pub version: u8,
+    pub functions: Vec<FunctionType>,
+    pub code: Vec<Vec<u8>>,
+    pub data: Option<Vec<u8>>,
+    pub subcontainers: Vec<DecodedEOF>,
+}
 
-    // Iterate through code sections for instruction validation (EIP-3670)
-    for (idx, header) in container.header.section_headers.iter().enumerate() {
-        if header.kind == SectionKind::Code {
-            let code = &container.sections[idx];
-            let mut i = 0;
-            while i < code.len() {
-                let opcode = code[i];
-
-                match opcode {
-                    // EIP-3670: INVALID and SELFDESTRUCT are invalid
-                    INVALID => return Err(EOFError::InvalidOpcode(opcode)),
-                    SELFDESTRUCT => return Err(EOFError::InvalidOpcode(opcode)),
-                    // EIP-4750: JUMP, JUMPI, PC are forbidden
-                    JUMP | JUMPI | PC => return Err(EOFError::JumpDestForbidden(opcode)),
-                    // PUSH opcodes
-                    PUSH1..=PUSH32 => {
-                        let push_size = (opcode - PUSH1 + 1) as usize;
-                        if i + 1 + push_size > code.len() {
-                            return Err(EOFError::TruncatedPushData);
-                        }
-                        i += push_size; // Skip push data bytes
-                    },
-                    // Placeholder for other specific invalid opcodes as per EIP-3670
-                    // For a stub, we assume other opcodes are valid or will be caught by future validation
-                    _ => {}
-                }
-                i += 1;
-            }
+/// Parses `bytecode` and validates the result with the default rules.
+pub fn parse_and_validate(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
+    let container = parse_eof_container(bytecode)?;
+    validate_eof_container(&container)?;
+    Ok(container)
+}
+
+/// Full validation entry point: parses `bytecode` and runs every check, returning
+/// the container if it is valid. Stages run in this order, and an error from an
+/// earlier stage is always reported ahead of any later one:
+///
+/// 1. Parsing: magic, version, section headers and bodies (`parse_eof_container`).
+/// 2. Container rules: section layout and sizes, per-section instructions and
+///    relative jumps, CALLF targets and subcontainer rules (`validate_eof_container`).
+/// 3. Type section: decoded into one `FunctionType` per code section, each of
+///    which must be consistent with its section (`validate_type_entries`).
+/// 4. EIP-5450 stack analysis of each code section in order, requiring the declared
+///    `max_stack_height` to match (`validate_declared_max_stack`).
+///
+/// Stages 3 and 4 are then repeated for each subcontainer, depth first.
+pub fn validate_eof(bytecode: &[u8]) -> Result<EOFContainer, EOFError> {
+    let container = parse_and_validate(bytecode)?;
+    validate_container_stacks(&container)?;
+    Ok(container)
+}
+
+/// Checks that Type entry `i` can describe code section `i`: entry 0 is the
+/// non-returning, input-less entry point; inputs and outputs are at most 127
+/// (or `NON_RETURNING_FUNCTION` for outputs); `max_stack_height` is within
+/// `MAX_STACK_HEIGHT` and covers the inputs; and a section using RETF is returning.
+fn validate_type_entries(container: &EOFContainer, types: &[FunctionType]) -> Result<(), EOFError> {
+    for (type_index, (ft, code)) in types.iter().zip(container.code_sections()).enumerate() {
+        let entry_ok = type_index != 0 || (ft.inputs == 0 && ft.is_non_returning());
+        let counts_ok = ft.inputs <= 0x7F && (ft.outputs <= 0x7F || ft.is_non_returning());
+        let max_ok = ft.max_stack_height <= MAX_STACK_HEIGHT && ft.max_stack_height >= ft.inputs as u16;
+        let uses_retf = instructions(code).map_while(Result::ok).any(|instr| instr.opcode == RETF);
+        if !entry_ok || !counts_ok || !max_ok || (uses_retf && ft.is_non_returning()) {
+            return Err(EOFError::TypeCodeMismatch { type_index });
         }
     }
+    Ok(())
+}
 
-    // EIP-3540: `types_size` must be divisible by 4, and the number of code sections must equal `types_size / 4`
-    // Find Type section header
-    let type_section_header = container.header.section_headers.iter()
-        .find(|h| h.kind == SectionKind::Type)
-        .ok_or(EOFError::MissingTerminator)?; // Already checked, but for safety
-
-    if type_section_header.size % 4 != 0 {
-        return Err(EOFError::MalformedSectionHeader); // Type section size must be a multiple of 4
+/// Stages 3 and 4 of `validate_eof` for `container` and its subcontainers.
+fn validate_container_stacks(container: &EOFContainer) -> Result<(), EOFError> {
+    let types = container.function_types()?;
+    validate_type_entries(container, &types)?;
+    for (section, code) in container.code_sections().enumerate() {
+        validate_declared_max_stack(code, &types, section)?;
     }
-    if (type_section_header.size / 4) as usize != code_section_count {
-        return Err(EOFError::MalformedSectionHeader); // Number of code sections must match type section entries
+    let subcontainer_count = container.header.section_headers.iter().filter(|h| h.kind == SectionKind::Container).count();
+    for index in 0..subcontainer_count {
+        validate_container_stacks(&container.subcontainer(index)?)?;
     }
-
     Ok(())
 }
 
-// Simple stack for simulation
-pub struct SimulatedStack(Vec<u8>);
+/// Parses and validates `bytecode`, returning the structured view of the
+/// container and, recursively, its subcontainers.
+pub fn parse_decoded(bytecode: &[u8]) -> Result<DecodedEOF, EOFError> {
+    decode_container(&parse_and_validate(bytecode)?)
+}
 
-impl SimulatedStack {
-    pub fn new() -> Self {
-        SimulatedStack(Vec::new())
+fn decode_container(container: &EOFContainer) -> Result<DecodedEOF, EOFError> {
+    let mut data = None;
+    let mut subcontainers = Vec::new();
+    for (header, body) in container.header.section_headers.iter().zip(&container.sections) {
+        match header.kind {
+            SectionKind::Data => data = Some(body.clone()),
+            SectionKind::Container => subcontainers.push(decode_container(&parse_eof_container(body)?)?),
+            SectionKind::Type | SectionKind::Code => {},
+        }
     }
+    Ok(DecodedEOF {
+        version: container.header.version,
+        functions: container.function_types()?,
+        code: container.code_sections().map(<[u8]>::to_vec).collect(),
+        data,
+        subcontainers,
+    })
+}
 
-    pub fn push(&mut self, val: u8) -> Result<(), EOFError> {
-        // Simple overflow check, can be more sophisticated
-        if self.0.len() >= 1024 { // Assuming a max stack depth of 1024 for this prototype
-            return Err(EOFError::StackOverflow);
-        }
-        self.0.push(val);
-        Ok(())
+/// Returns the encoded size of `container` once `appended_data_len` bytes are
+/// appended to its Data section (adding a Data header if it has none). Fails if
+/// the grown Data section no longer fits its u16 size field or the section
+/// bodies exceed `MAX_CONTAINER_SIZE`.
+pub fn deployed_size(container: &EOFContainer, appended_data_len: usize) -> Result<usize, EOFError> {
+    let data_len = container.data_section().map_or(0, <[u8]>::len) + appended_data_len;
+    if data_len > u16::MAX as usize {
+        return Err(EOFError::SectionTooLarge(data_len));
+    }
+    let body_size = container.sections.iter().map(Vec::len).sum::<usize>() + appended_data_len;
+    if body_size > MAX_CONTAINER_SIZE {
+        return Err(EOFError::ContainerTooLarge);
     }
+    let added_header = if container.data_section().is_none() && appended_data_len > 0 { 3 } else { 0 };
+    Ok(container.header.encoded_len() + added_header + body_size)
+}
 
-    pub fn pop(&mut self) -> Result<u8, EOFError> {
-        self.0.pop().ok_or(EOFError::StackUnderflow)
+/// Returns true if `a` and `b` have the same version and identical Type, Code and
+/// Container sections, ignoring any Data section. Unlike comparing `without_data()`
+/// copies, nothing is allocated.
+pub fn code_identical(a: &EOFContainer, b: &EOFContainer) -> bool {
+    fn non_data(c: &EOFContainer) -> impl Iterator<Item = (&SectionHeader, &Vec<u8>)> {
+        c.header.section_headers.iter()
+            .zip(&c.sections)
+            .filter(|(header, _)| header.kind != SectionKind::Data)
     }
+    a.header.version == b.header.version && non_data(a).eq(non_data(b))
+}
 
-    #[cfg(test)]
-    pub fn len(&self) -> usize {
-        self.0.len()
+/// Returns true if `bytecode` parses as an EOF container.
+pub fn is_eof(bytecode: &[u8]) -> bool {
+    parse_eof_container(bytecode).is_ok()
+}
+
+/// Rejects EOF containers for contexts where only legacy bytecode is allowed.
+pub fn reject_eof(bytecode: &[u8]) -> Result<(), EOFError> {
+    if is_eof(bytecode) {
+        return Err(EOFError::UnexpectedEof);
     }
+    Ok(())
 }
 
-/// Simulates a single step of EOF code execution, focusing on control flow.
-/// This is a simplified prototype, not a full EVM interpreter.
-pub fn simulate_eof_step(
-    code_section: &[u8],
-    pc: &mut usize,
-    stack: &mut SimulatedStack,
-) -> Result<(), EOFError> {
-    if *pc >= code_section.len() {
-        return Err(EOFError::UnexpectedEndOfInput); // Out of bounds
+/// Toggles for validation rules that callers may want to relax (e.g. when
+/// validating against a fork that has not activated the corresponding EIP).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValidationConfig {
+    /// EIP-7620: reject CREATE/CREATE2 in favor of EOFCREATE.
+    pub forbid_legacy_create: bool,
+    /// EIP-3540: reject code introspection and CALLCODE opcodes removed in EOF.
+    pub forbid_removed_opcodes: bool,
+    /// EIP-7620: treat an unreferenced Container section as an error rather than a warning.
+    pub strict_subcontainer_references: bool,
+    /// EIP-663: accept DUPN, SWAPN and EXCHANGE.
+    pub allow_eip663: bool,
+    /// Accept MLOAD, MSTORE, MSTORE8 and MSIZE; restricted profiles may disable memory.
+    pub allow_memory_ops: bool,
+    /// Turn every `EOFWarning` (see `validation_warnings`) into an error.
+    pub deny_warnings: bool,
+    /// EIP-7620: report a RETURNCONTRACT-deployed subcontainer that fails to parse
+    /// as `InvalidReturnedContainer` rather than with the underlying parse error.
+    pub check_returned_containers: bool,
+    /// Restricted profiles: when set, only these opcodes (plus terminating opcodes)
+    /// are accepted; anything else fails with `OpcodeNotAllowed`.
+    pub allowed_opcodes: Option<HashSet<u8>>,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            forbid_legacy_create: true,
+            forbid_removed_opcodes: true,
+            strict_subcontainer_references: false,
+            allow_eip663: true,
+            allow_memory_ops: true,
+            deny_warnings: false,
+            check_returned_containers: false,
+            allowed_opcodes: None,
+        }
     }
+}
 
-    let opcode = code_section[*pc];
-    match opcode {
-        // --- Existing opcodes (for context, simplified handling) ---
-        PUSH1..=PUSH32 => {
-            let push_size = (opcode - PUSH1 + 1) as usize;
-            if *pc + 1 + push_size > code_section.len() {
-                return Err(EOFError::TruncatedPushData); // Should be caught by validation
+impl ValidationConfig {
+    /// Rule set of the original Prague EOF scope: the EOF base rules without EIP-663.
+    pub fn prague() -> Self {
+        ValidationConfig { allow_eip663: false, ..ValidationConfig::default() }
+    }
+
+    /// Rule set of the Osaka EOF scope, which adds EIP-663. Same as `default()`.
+    pub fn osaka() -> Self {
+        ValidationConfig::default()
+    }
+}
+
+/// Validates an EOFContainer according to EIP-3670 and related EIPs.
+pub fn validate_eof_container(container: &EOFContainer) -> Result<(), EOFError> {
+    validate_eof_container_with_config(container, &ValidationConfig::default())
+}
+
+/// Validates an EOFContainer, enabling only the optional rules selected in `config`.
+pub fn validate_eof_container_with_config(container: &EOFContainer, config: &ValidationConfig) -> Result<(), EOFError> {
+    validate_container(container, config, true)
+}
+
+/// Validates every container in the tree rooted at `container` and collects all
+/// failures, each tagged with the path of Container section indices leading to
+/// the failing container (`[]` is `container` itself, `[0, 2]` is subcontainer 2
+/// of subcontainer 0). Each container reports at most its first error.
+pub fn validate_eof_deep(container: &EOFContainer) -> Result<(), Vec<(Vec<usize>, EOFError)>> {
+    fn visit(container: &EOFContainer, path: &mut Vec<usize>, errors: &mut Vec<(Vec<usize>, EOFError)>) {
+        if let Err(err) = validate_container(container, &ValidationConfig::default(), false) {
+            errors.push((path.clone(), err));
+        }
+        let subcontainers = container.header.section_headers.iter().zip(&container.sections)
+            .filter(|(header, _)| header.kind == SectionKind::Container);
+        for (index, (_, body)) in subcontainers.enumerate() {
+            // A subcontainer that fails to parse is reported by its parent
+            if let Ok(subcontainer) = parse_eof_container(body) {
+                path.push(index);
+                visit(&subcontainer, path, errors);
+                path.pop();
             }
-            // For simulation, just push a dummy value (or the first byte of the data)
-            stack.push(opcode)?; // Push opcode for a placeholder
-            *pc += push_size + 1;
-        },
-        // A simple opcode that consumes one stack item and does nothing
-        0x01 => { // ADD for example
-            stack.pop()?; // Consume two, push one. Simplified.
-            stack.pop()?;
-            stack.push(0)?; // Dummy result
-            *pc += 1;
+        }
+    }
+    let mut errors = Vec::new();
+    visit(container, &mut Vec::new(), &mut errors);
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+/// Shared body of `validate_eof_container_with_config`; subcontainers are only
+/// parsed and checked against their parent when `recurse` is false.
+fn validate_container(container: &EOFContainer, config: &ValidationConfig, recurse: bool) -> Result<(), EOFError> {
+    validate_structure(container)?;
+
+    if container.is_initcode() {
+        validate_initcode_size(container.header.encoded_len() + container.sections.iter().map(Vec::len).sum::<usize>())?;
+    }
+
+    // EIP-7480: DATALOADN is checked against the *declared* data size, since the
+    // data section body may be deferred and appended at deploy time.
+    let declared_data_size = declared_data_size(container);
+
+    // Iterate through code sections for instruction validation (EIP-3670)
+    let types = container.function_types()?;
+    for code in container.code_sections() {
+        validate_code_section(code, &types, config, declared_data_size)?;
+    }
+
+    // The entry point must start an instruction of code section 0
+    container.offset_to_index(0, container.entry_pc())?;
+
+    if config.strict_subcontainer_references {
+        if let Some(idx) = unreferenced_subcontainers(container).next() {
+            return Err(EOFError::UnreferencedSubcontainer(idx));
+        }
+    }
+    if config.deny_warnings {
+        if let Some(warning) = validation_warnings(container).into_iter().next() {
+            return Err(warning.into());
+        }
+    }
+
+    // EIP-7620: containers deployed by RETURNCONTRACT are runtime code and EOFCREATE
+    // targets are initcode. A top-level container is not checked against its
+    // context, since `is_initcode` only guesses its kind from the opcodes it uses.
+    let runtime_indices: Vec<u8> = container.code_sections().flat_map(returncontract_targets).collect();
+    let eofcreate_indices: Vec<u8> = container.code_sections().flat_map(eofcreate_targets).collect();
+
+    let subcontainer_count = container.header.section_headers.iter().filter(|h| h.kind == SectionKind::Container).count();
+    validate_subcontainer_indices(&runtime_indices, &eofcreate_indices, subcontainer_count)?;
+
+    let subcontainer_sections = container.header.section_headers.iter().zip(&container.sections)
+        .filter(|(header, _)| header.kind == SectionKind::Container);
+    for (container_index, (_, body)) in subcontainer_sections.enumerate() {
+        let is_runtime = runtime_indices.contains(&(container_index as u8));
+        let is_eofcreate_target = eofcreate_indices.contains(&(container_index as u8));
+        validate_subcontainer(container_index, body, is_runtime, is_eofcreate_target, config, recurse)?;
+    }
+
+    Ok(())
+}
+
+// The rules below are shared by `validate_container` and `validate_eof_reader`,
+// so the in-memory and streaming validators cannot drift apart.
+
+/// EIP-3860: initcode, including its subcontainers, is capped at `MAX_INITCODE_SIZE`.
+fn validate_initcode_size(size: usize) -> Result<(), EOFError> {
+    if size > MAX_INITCODE_SIZE {
+        return Err(EOFError::InitcodeTooLarge(size));
+    }
+    Ok(())
+}
+
+/// Checks one code section: opcodes (EIP-3670), relative jumps (EIP-4200) and
+/// CALLF targets against the container's Type entries (EIP-4750/6206).
+fn validate_code_section(code: &[u8], types: &[FunctionType], config: &ValidationConfig, declared_data_size: usize) -> Result<(), EOFError> {
+    validate_code_opcodes(code, config, declared_data_size).map_err(|(_, err)| err)?;
+    validate_relative_jumps(code)?;
+    validate_code_callf_targets(code, types)
+}
+
+/// EIP-7620: EOFCREATE and RETURNCONTRACT must name an existing Container section.
+fn validate_subcontainer_indices(runtime_indices: &[u8], eofcreate_indices: &[u8], subcontainer_count: usize) -> Result<(), EOFError> {
+    match runtime_indices.iter().chain(eofcreate_indices).find(|&&index| index as usize >= subcontainer_count) {
+        Some(&dangling) => Err(EOFError::InvalidSectionIndex(dangling as usize)),
+        None => Ok(()),
+    }
+}
+
+/// Checks Container section `index` against its parent's references to it:
+/// RETURNCONTRACT targets are runtime code, EOFCREATE targets must be initcode.
+/// With `recurse` the subcontainer is also validated with the same rules, so
+/// forbidden opcodes cannot hide inside it (EIP-7620).
+fn validate_subcontainer(index: usize, body: &[u8], is_runtime: bool, is_eofcreate_target: bool, config: &ValidationConfig, recurse: bool) -> Result<(), EOFError> {
+    let subcontainer = match parse_eof_container(body) {
+        Err(_) if is_runtime && config.check_returned_containers => {
+            return Err(EOFError::InvalidReturnedContainer(index));
         },
-        // --- EOF Jumps (EIP-4200) ---
-        RJUMP => {
-            if *pc + 3 > code_section.len() { // opcode + 2-byte immediate
-                return Err(EOFError::UnexpectedEndOfInput);
+        parsed => parsed?,
+    };
+    if is_runtime {
+        // EIP-170: the deployed runtime container is capped at `MAX_CODE_SIZE`
+        if body.len() > MAX_CODE_SIZE {
+            return Err(EOFError::RuntimeCodeTooLarge(body.len()));
+        }
+        validate_opcode_context(&subcontainer, false)?;
+    }
+    if recurse {
+        validate_container(&subcontainer, config, true)?;
+    }
+    // EIP-7620: EOFCREATE runs its target as initcode
+    if is_eofcreate_target {
+        if !subcontainer.is_initcode() {
+            return Err(EOFError::EofCreateTargetNotInitcode(index));
+        }
+        validate_opcode_context(&subcontainer, true)?;
+    }
+    Ok(())
+}
+
+/// Returns the container indices referenced by RETURNCONTRACT in `code`.
+fn returncontract_targets(code: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    instructions(code)
+        .map_while(Result::ok)
+        .filter(|instr| instr.opcode == RETURNCONTRACT)
+        .map(|instr| instr.immediate[0])
+}
+
+/// Returns the container indices referenced by EOFCREATE in `code`.
+fn eofcreate_targets(code: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    instructions(code)
+        .map_while(Result::ok)
+        .filter(|instr| instr.opcode == EOFCREATE)
+        .map(|instr| instr.immediate[0])
+}
+
+/// Where an opcode may appear: in initcode, in runtime code, or in both.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OpcodeContext {
+    Both,
+    InitcodeOnly,
+    RuntimeOnly,
+}
+
+/// EIP-7620: classifies `opcode` by the container kind it may appear in.
+/// RETURNCONTRACT only makes sense in initcode, while initcode must end in
+/// RETURNCONTRACT or REVERT rather than STOP or RETURN.
+pub fn opcode_context(opcode: u8) -> OpcodeContext {
+    match opcode {
+        RETURNCONTRACT => OpcodeContext::InitcodeOnly,
+        STOP | RETURN => OpcodeContext::RuntimeOnly,
+        _ => OpcodeContext::Both,
+    }
+}
+
+/// Checks every opcode of `container` against its context: `initcode` says
+/// whether the container runs as initcode or as runtime code.
+fn validate_opcode_context(container: &EOFContainer, initcode: bool) -> Result<(), EOFError> {
+    let forbidden = if initcode { OpcodeContext::RuntimeOnly } else { OpcodeContext::InitcodeOnly };
+    let misplaced = container.code_sections()
+        .flat_map(|code| instructions(code).map_while(Result::ok))
+        .find(|instr| opcode_context(instr.opcode) == forbidden);
+    match misplaced {
+        Some(instr) if instr.opcode == RETURNCONTRACT => Err(EOFError::ReturnContractInRuntime),
+        Some(instr) => Err(EOFError::OpcodeWrongContext(instr.opcode)),
+        None => Ok(()),
+    }
+}
+
+/// Validates an EOF container read from `reader` with the default rules.
+/// The headers and Type section are buffered first; every later section is
+/// validated as soon as its body is read, so only one body is held at a time
+/// and reading stops at the first invalid section.
+pub fn validate_eof_reader<R: Read>(mut reader: R) -> Result<(), EOFError> {
+    let config = ValidationConfig::default();
+
+    // Buffer magic and version. The bytes of a short prefix are kept (`read_exact`
+    // would discard them) so that `parse_header` reports the same error as for
+    // in-memory input: NotEOF or InvalidMagic before UnexpectedEndOfInput.
+    let mut header_bytes = Vec::with_capacity(3);
+    (&mut reader).take(3).read_to_end(&mut header_bytes).map_err(|_| EOFError::UnexpectedEndOfInput)?;
+    if header_bytes.len() < 3 || header_bytes[..2] != EOF_MAGIC.to_be_bytes() || header_bytes[2] != EOF_VERSION {
+        return parse_header(&header_bytes).map(|_| ());
+    }
+
+    let mut read = |len: usize| -> Result<Vec<u8>, EOFError> {
+        let mut buf = vec![0u8; len];
+        reader.read_exact(&mut buf).map_err(|_| EOFError::UnexpectedEndOfInput)?;
+        Ok(buf)
+    };
+
+    // Buffer the section headers up to the terminator. A short read ends the
+    // loop and leaves `parse_header` to report the truncation.
+    while let Ok(kind) = read(1) {
+        header_bytes.push(kind[0]);
+        if kind[0] == 0x00 {
+            break;
+        }
+        SectionKind::try_from(kind[0])?;
+        match read(2) {
+            Ok(size) => header_bytes.extend(size),
+            Err(_) => break,
+        }
+    }
+    let (header, _) = parse_header(&header_bytes)?;
+    let total_declared_size: usize = header.section_headers.iter().map(|h| h.size as usize).sum();
+    if total_declared_size > MAX_CONTAINER_SIZE {
+        return Err(EOFError::ContainerTooLarge);
+    }
+
+    // Structural rules need only the headers and the (first) Type body
+    let mut skeleton = EOFContainer { sections: vec![Vec::new(); header.section_headers.len()], header };
+    skeleton.sections[0] = read(skeleton.header.section_headers[0].size as usize)?;
+    validate_section_layout(&skeleton)?;
+    let types = skeleton.function_types()?;
+    let declared_data_size = declared_data_size(&skeleton);
+
+    // The layout puts every Code section right after the Type section, so the
+    // container-wide rules can be checked before any subcontainer is read.
+    let code_count = skeleton.header.section_headers.iter().filter(|h| h.kind == SectionKind::Code).count();
+    let mut runtime_indices = Vec::new();
+    let mut eofcreate_indices = Vec::new();
+    for idx in 1..=code_count {
+        let body = read(skeleton.header.section_headers[idx].size as usize)?;
+        validate_code_section(&body, &types, &config, declared_data_size)?;
+        runtime_indices.extend(returncontract_targets(&body));
+        eofcreate_indices.extend(eofcreate_targets(&body));
+        // Code section 0 is kept for the entry point check
+        if idx == 1 {
+            skeleton.sections[idx] = body;
+        }
+    }
+
+    // Same classification as `EOFContainer::is_initcode`, from the collected targets
+    let subcontainer_count = skeleton.header.section_headers.iter().filter(|h| h.kind == SectionKind::Container).count();
+    if !runtime_indices.is_empty() || (!eofcreate_indices.is_empty() && subcontainer_count > 0) {
+        validate_initcode_size(skeleton.header.encoded_len() + total_declared_size)?;
+    }
+    skeleton.offset_to_index(0, skeleton.entry_pc())?;
+    validate_subcontainer_indices(&runtime_indices, &eofcreate_indices, subcontainer_count)?;
+
+    let mut container_index = 0;
+    for header in &skeleton.header.section_headers[code_count + 1..] {
+        let body = read(header.size as usize)?;
+        if header.kind == SectionKind::Container {
+            let is_runtime = runtime_indices.contains(&(container_index as u8));
+            let is_eofcreate_target = eofcreate_indices.contains(&(container_index as u8));
+            validate_subcontainer(container_index, &body, is_runtime, is_eofcreate_target, &config, true)?;
+            container_index += 1;
+        }
+    }
+
+    // EIP-3540: no stray bytes after the declared sections
+    if read(1).is_ok() {
+        return Err(EOFError::SectionSizeMismatch);
+    }
+    Ok(())
+}
+
+/// Validates only the structural EOF rules: section order, section counts,
+/// non-empty Type/Code sections, the Type entry / Code section correspondence and
+/// the agreement of section bodies with their declared sizes.
+/// Opcodes and stack heights are not inspected.
+pub fn validate_structure(container: &EOFContainer) -> Result<(), EOFError> {
+    // Hand-built containers may pair headers and bodies inconsistently
+    if container.header.section_headers.len() != container.sections.len() {
+        return Err(EOFError::MalformedSectionHeader);
+    }
+    validate_section_layout(container)?;
+
+    // ...or with bodies that disagree with the declared sizes. The Data body is
+    // excluded since it may legitimately be deferred (see `data_padding`).
+    let mismatched = container.header.section_headers.iter()
+        .zip(&container.sections)
+        .filter(|(header, _)| header.kind != SectionKind::Data)
+        .any(|(header, body)| header.size as usize != body.len());
+    if mismatched {
+        return Err(EOFError::SectionSizeMismatch);
+    }
+    Ok(())
+}
+
+/// The structural rules that need only the section headers and the Type body.
+fn validate_section_layout(container: &EOFContainer) -> Result<(), EOFError> {
+    let mut code_section_count = 0;
+    let mut data_section_found = false;
+
+    let mut section_kind_order: Vec<SectionKind> = Vec::new();
+
+    for (idx, header) in container.header.section_headers.iter().enumerate() {
+        // EIP-3540: Section order validation (Type, Code, Container, Data)
+        // Simplified for prototype: Type must be first, Data must be last if present, and
+        // Code and Container sections each form one contiguous block.
+        match header.kind {
+            SectionKind::Type => {
+                if idx != 0 {
+                    return Err(EOFError::MalformedSectionHeader); // Type must be first
+                }
+                section_kind_order.push(header.kind);
+            },
+            SectionKind::Code => {
+                code_section_count += 1;
+                if section_kind_order.last() != Some(&SectionKind::Type) && section_kind_order.last() != Some(&SectionKind::Code) {
+                    // Code sections must follow Type section or other Code sections
+                    return Err(EOFError::MalformedSectionHeader);
+                }
+                section_kind_order.push(header.kind);
+            },
+            SectionKind::Data => {
+                data_section_found = true;
+                if section_kind_order.last() != Some(&SectionKind::Code) && section_kind_order.last() != Some(&SectionKind::Container) {
+                    // Data follows the last Code or Container section
+                    return Err(EOFError::MalformedSectionHeader);
+                }
+                section_kind_order.push(header.kind);
+            },
+            SectionKind::Container => {
+                if data_section_found {
+                    return Err(EOFError::MalformedSectionHeader); // Container must not follow Data
+                }
+                if section_kind_order.last() != Some(&SectionKind::Code) && section_kind_order.last() != Some(&SectionKind::Container) {
+                    // Container sections must directly follow the Code block or another Container
+                    return Err(EOFError::MalformedSectionHeader);
+                }
+                section_kind_order.push(header.kind);
+            },
+        }
+
+        if header.size == 0 && (header.kind == SectionKind::Type || header.kind == SectionKind::Code) {
+            return Err(EOFError::SectionSizeMismatch); // Type and Code sections cannot be empty
+        }
+    }
+
+    if code_section_count == 0 {
+        return Err(EOFError::MissingCodeSection); // EIP-3540: Must have at least one code section
+    }
+
+    // EIP-3540: `types_size` must be divisible by 4, and the number of code sections must equal `types_size / 4`
+    // Find Type section header
+    let type_section_header = container.header.section_headers.iter()
+        .find(|h| h.kind == SectionKind::Type)
+        .ok_or(EOFError::MissingTerminator)?; // Already checked, but for safety
+
+    if type_section_header.size % 4 != 0 {
+        return Err(EOFError::TypeSectionSizeNotMultipleOf4(type_section_header.size));
+    }
+    if (type_section_header.size / 4) as usize != code_section_count {
+        return Err(EOFError::MalformedSectionHeader); // Number of code sections must match type section entries
+    }
+    // Defense in depth for hand-built containers: the Type body itself must hold one entry per code section
+    if container.function_types()?.len() != container.code_sections().count() {
+        return Err(EOFError::MalformedSectionHeader);
+    }
+
+    Ok(())
+}
+
+/// Checks that `container` has exactly `expected` code sections (functions), e.g.
+/// as known from the compiler output, to catch linking mistakes.
+pub fn validate_function_count(container: &EOFContainer, expected: usize) -> Result<(), EOFError> {
+    let found = container.code_sections().count();
+    if found != expected {
+        return Err(EOFError::FunctionCountMismatch { expected, found });
+    }
+    Ok(())
+}
+
+/// Validates `container` and, on success, returns its decoded Type section entries.
+pub fn validate_and_decode(container: &EOFContainer) -> Result<Vec<FunctionType>, EOFError> {
+    validate_eof_container(container)?;
+    container.function_types()
+}
+
+/// Soft issues that do not make a container invalid by default.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EOFWarning {
+    UnreferencedSubcontainer(usize), // Container section index never used by EOFCREATE/RETURNCONTRACT
+    UnusedDataSection, // Non-empty Data section with no DATALOAD/DATALOADN/DATASIZE/DATACOPY
+    DataCopyOutOfBounds { section: usize, offset: usize }, // DATACOPY whose constant arguments read past the declared data
+}
+
+impl std::fmt::Display for EOFWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EOFWarning::UnreferencedSubcontainer(idx) => write!(f, "Subcontainer {} is never referenced", idx),
+            EOFWarning::UnusedDataSection => write!(f, "Data section is never read"),
+            EOFWarning::DataCopyOutOfBounds { section, offset } => {
+                write!(f, "DATACOPY at offset {} of code section {} reads past the data section", offset, section)
+            },
+        }
+    }
+}
+
+/// The hard error a warning becomes under `ValidationConfig::deny_warnings`.
+impl From<EOFWarning> for EOFError {
+    fn from(warning: EOFWarning) -> Self {
+        match warning {
+            EOFWarning::UnreferencedSubcontainer(idx) => EOFError::UnreferencedSubcontainer(idx),
+            EOFWarning::UnusedDataSection => EOFError::UnusedDataSection,
+            EOFWarning::DataCopyOutOfBounds { section, offset } => EOFError::DataCopyOutOfBounds { section, offset },
+        }
+    }
+}
+
+/// Collects soft issues in `container` that validation tolerates by default.
+pub fn validation_warnings(container: &EOFContainer) -> Vec<EOFWarning> {
+    let mut warnings: Vec<EOFWarning> = unreferenced_subcontainers(container).map(EOFWarning::UnreferencedSubcontainer).collect();
+    let reads_data = container.code_sections()
+        .flat_map(|code| instructions(code).map_while(Result::ok))
+        .any(|instr| matches!(instr.opcode, DATALOAD | DATALOADN | DATASIZE | DATACOPY));
+    if declared_data_size(container) > 0 && !reads_data {
+        warnings.push(EOFWarning::UnusedDataSection);
+    }
+    warnings.extend(static_datacopy_overruns(container));
+    warnings
+}
+
+/// Returns the value pushed by a PUSH0..PUSH32 instruction, saturated to u64.
+fn push_constant(instr: &Instruction) -> Option<u64> {
+    if !(PUSH0..=PUSH32).contains(&instr.opcode) {
+        return None;
+    }
+    let significant = instr.immediate.iter().skip_while(|&&b| b == 0).count();
+    if significant > 8 {
+        return Some(u64::MAX);
+    }
+    Some(instr.immediate.iter().fold(0u64, |value, &b| value << 8 | b as u64))
+}
+
+/// Best-effort EIP-7480 check: a DATACOPY directly preceded by `PUSH size PUSH offset
+/// PUSH mem_offset` must stay within the declared data size. DATACOPY with any
+/// dynamically computed argument is skipped.
+fn static_datacopy_overruns(container: &EOFContainer) -> Vec<EOFWarning> {
+    let data_size = declared_data_size(container) as u64;
+    let mut warnings = Vec::new();
+    for (section, code) in container.code_sections().enumerate() {
+        let instrs: Vec<Instruction> = instructions(code).map_while(Result::ok).collect();
+        for window in instrs.windows(4) {
+            if window[3].opcode != DATACOPY {
+                continue;
             }
-            let offset_bytes = &code_section[*pc + 1 .. *pc + 3];
-            let offset = i16::from_be_bytes(offset_bytes.try_into().unwrap());
-            *pc = (*pc as isize + 3 + offset as isize) as usize;
-        },
-        RJUMPI => {
-            if *pc + 3 > code_section.len() { // opcode + 2-byte immediate
-                return Err(EOFError::UnexpectedEndOfInput);
+            if let (Some(size), Some(offset), Some(_)) = (push_constant(&window[0]), push_constant(&window[1]), push_constant(&window[2])) {
+                if offset.saturating_add(size) > data_size {
+                    warnings.push(EOFWarning::DataCopyOutOfBounds { section, offset: window[3].offset });
+                }
             }
-            let condition = stack.pop()?;
-            let offset_bytes = &code_section[*pc + 1 .. *pc + 3];
-            let offset = i16::from_be_bytes(offset_bytes.try_into().unwrap());
+        }
+    }
+    warnings
+}
+
+/// Yields the indices of Container sections no EOFCREATE/RETURNCONTRACT refers to.
+fn unreferenced_subcontainers(container: &EOFContainer) -> impl Iterator<Item = usize> {
+    let referenced: BTreeSet<usize> = container.subcontainer_references().into_iter()
+        .map(|(_, _, index)| index as usize)
+        .collect();
+    let count = container.header.section_headers.iter().filter(|h| h.kind == SectionKind::Container).count();
+    (0..count).filter(move |idx| !referenced.contains(idx))
+}
+
+/// EIP-4750/6206: every CALLF in `code` must target an existing code section whose
+/// function returns; non-returning functions may only be entered via JUMPF.
+fn validate_code_callf_targets(code: &[u8], types: &[FunctionType]) -> Result<(), EOFError> {
+    for instr in instructions(code) {
+        let instr = instr?;
+        if instr.opcode != CALLF {
+            continue;
+        }
+        let target = read_u16_be(instr.immediate, 0)?;
+        let target_type = types.get(target as usize).ok_or(EOFError::InvalidSectionIndex(target as usize))?;
+        if target_type.is_non_returning() {
+            return Err(EOFError::CallfToNonReturning(target));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the Data section size declared in the header (0 if there is none),
+/// which may exceed the present body when data is appended at deploy time.
+fn declared_data_size(container: &EOFContainer) -> usize {
+    container.header.section_headers.iter()
+        .find(|h| h.kind == SectionKind::Data)
+        .map_or(0, |h| h.size as usize)
+}
+
+/// Scans a single code section for forbidden opcodes and malformed immediates
+/// (EIP-3670 and related EIPs), returning the offset of the first offending instruction.
+fn validate_code_opcodes(code: &[u8], config: &ValidationConfig, declared_data_size: usize) -> Result<(), (usize, EOFError)> {
+    let mut i = 0;
+    while i < code.len() {
+        let opcode = code[i];
+
+        if let Some(allowed) = &config.allowed_opcodes {
+            if !allowed.contains(&opcode) && !is_terminating(opcode) {
+                return Err((i, EOFError::OpcodeNotAllowed(opcode)));
+            }
+        }
+
+        match opcode {
+            // EIP-3670: INVALID and SELFDESTRUCT are invalid
+            INVALID => return Err((i, EOFError::InvalidOpcode(opcode))),
+            SELFDESTRUCT => return Err((i, EOFError::InvalidOpcode(opcode))),
+            // EIP-4750: JUMP, JUMPI, PC are forbidden
+            JUMP | JUMPI | PC => return Err((i, EOFError::JumpDestForbidden(opcode))),
+            // EIP-7620: CREATE and CREATE2 are replaced by EOFCREATE
+            CREATE | CREATE2 if config.forbid_legacy_create => {
+                return Err((i, EOFError::LegacyCreateForbidden(opcode)));
+            },
+            // EIP-3540: code introspection and CALLCODE are removed (GAS is retained)
+            CODESIZE | CODECOPY | EXTCODESIZE | EXTCODECOPY | EXTCODEHASH | CALLCODE if config.forbid_removed_opcodes => {
+                return Err((i, EOFError::OpcodeRemovedInEOF(opcode)));
+            },
+            // EIP-4750/6206: CALLF and JUMPF take a 2-byte code section index immediate
+            CALLF | JUMPF => {
+                if i + 3 > code.len() {
+                    return Err((i, EOFError::TruncatedImmediate(opcode)));
+                }
+                i += 2; // Skip immediate bytes
+            },
+            // EIP-7620: EOFCREATE and RETURNCONTRACT take a 1-byte container index immediate
+            EOFCREATE | RETURNCONTRACT => {
+                if i + 2 > code.len() {
+                    return Err((i, EOFError::TruncatedImmediate(opcode)));
+                }
+                i += 1; // Skip immediate byte
+            },
+            // PUSH opcodes
+            PUSH1..=PUSH32 => {
+                let push_size = (opcode - PUSH1 + 1) as usize;
+                if i + 1 + push_size > code.len() {
+                    return Err((i, EOFError::TruncatedPushData));
+                }
+                i += push_size; // Skip push data bytes
+            },
+            // EIP-7480: DATALOADN reads 32 bytes at a 2-byte immediate offset
+            DATALOADN => {
+                if i + 3 > code.len() {
+                    return Err((i, EOFError::TruncatedImmediate(opcode)));
+                }
+                let offset = read_u16_be(code, i + 1).map_err(|err| (i, err))?;
+                if offset as usize + 32 > declared_data_size {
+                    return Err((i, EOFError::DataloadnOutOfBounds(offset)));
+                }
+                i += 2; // Skip immediate bytes
+            },
+            // EIP-663: DUPN, SWAPN and EXCHANGE are only defined once the EIP is active
+            DUPN | SWAPN | EXCHANGE if !config.allow_eip663 => {
+                return Err((i, EOFError::InvalidOpcode(opcode)));
+            },
+            MLOAD | MSTORE | MSTORE8 | MSIZE if !config.allow_memory_ops => {
+                return Err((i, EOFError::OpcodeDisabledByConfig(opcode)));
+            },
+            // Remaining immediate-bearing opcodes (RJUMP, RJUMPI, RJUMPV, DUPN, SWAPN, EXCHANGE)
+            // must not spill past the end of the section; their immediates are skipped.
+            // Other opcodes are assumed valid or left to future validation.
+            _ => {
+                i += instruction_len(code, i).map_err(|err| (i, err))? - 1;
+            }
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Returns the location of the first opcode-validation failure as
+/// `(code section index, byte offset)`, or `None` if every code section passes.
+/// Only the per-instruction scan is run; structural rules are not checked.
+pub fn first_error_location(container: &EOFContainer) -> Option<(usize, usize)> {
+    let declared_data_size = declared_data_size(container);
+    let config = ValidationConfig::default();
+    container.code_sections().enumerate().find_map(|(section, code)| {
+        validate_code_opcodes(code, &config, declared_data_size).err().map(|(offset, _)| (section, offset))
+    })
+}
+
+/// Debug utility: greedily shrinks a container failing `validate_eof_container`
+/// (dropping Data and Container sections, trailing functions and single
+/// instructions) as long as it still fails with the same first error. Returns
+/// the smallest reproducer found, or `None` if `container` is valid.
+pub fn minimize_failing_container(container: &EOFContainer) -> Option<EOFContainer> {
+    let target = validate_eof_container(container).err()?;
+    let mut best = container.clone();
+    while let Some(smaller) = shrink_candidates(&best).into_iter()
+        .find(|candidate| validate_eof_container(candidate).as_ref().err() == Some(&target))
+    {
+        best = smaller;
+    }
+    Some(best)
+}
+
+/// Containers one step smaller than `container`, for `minimize_failing_container`.
+fn shrink_candidates(container: &EOFContainer) -> Vec<EOFContainer> {
+    let mut candidates = Vec::new();
+    let headers = &container.header.section_headers;
+    for (position, header) in headers.iter().enumerate() {
+        if matches!(header.kind, SectionKind::Data | SectionKind::Container) {
+            let mut candidate = container.clone();
+            candidate.header.section_headers.remove(position);
+            candidate.sections.remove(position);
+            candidates.push(candidate);
+        }
+    }
+
+    // Drop the last function together with its Type entry
+    let type_position = headers.iter().position(|header| header.kind == SectionKind::Type);
+    let last_code = headers.iter().rposition(|header| header.kind == SectionKind::Code);
+    if let (Some(type_position), Some(last_code)) = (type_position, last_code) {
+        if container.code_sections().count() > 1 && container.sections[type_position].len() >= 4 {
+            let mut candidate = container.clone();
+            candidate.header.section_headers.remove(last_code);
+            candidate.sections.remove(last_code);
+            let type_body = &mut candidate.sections[type_position];
+            type_body.truncate(type_body.len() - 4);
+            candidate.header.section_headers[type_position].size = type_body.len() as u16;
+            candidates.push(candidate);
+        }
+    }
+
+    for (index, code) in container.code_sections().enumerate() {
+        for instr in instructions(code).map_while(Result::ok) {
+            let end = instr.offset + 1 + instr.immediate.len();
+            let shorter = [&code[..instr.offset], &code[end..]].concat();
+            if let Ok(candidate) = container.replace_code_section(index, shorter) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// A single decoded instruction within a code section.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Instruction<'a> {
+    pub offset: usize,
+    pub opcode: u8,
+    pub immediate: &'a [u8],
+}
+
+/// Iterator over the instructions of a code section. Yields an error and stops
+/// if an instruction's immediate runs past the end of the section.
+pub struct InstructionIter<'a> {
+    code: &'a [u8],
+    pc: usize,
+}
+
+impl<'a> Iterator for InstructionIter<'a> {
+    type Item = Result<Instruction<'a>, EOFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pc >= self.code.len() {
+            return None;
+        }
+        let offset = self.pc;
+        match instruction_len(self.code, offset) {
+            Ok(len) => {
+                self.pc += len;
+                Some(Ok(Instruction { offset, opcode: self.code[offset], immediate: &self.code[offset + 1..offset + len] }))
+            },
+            Err(err) => {
+                self.pc = self.code.len(); // Stop after reporting the truncation
+                Some(Err(err))
+            },
+        }
+    }
+}
+
+/// Returns an iterator over the instructions in `code`, skipping immediates.
+pub fn instructions(code: &[u8]) -> InstructionIter<'_> {
+    InstructionIter { code, pc: 0 }
+}
+
+/// Returns the byte length of the instruction at `pc`, including its immediate.
+fn instruction_len(code: &[u8], pc: usize) -> Result<usize, EOFError> {
+    let opcode = code[pc];
+    let immediate_size = opcode_info(opcode).map_or(0, |info| info.immediate_size as usize);
+    let mut len = 1 + immediate_size;
+    if opcode == RJUMPV && pc + 1 < code.len() {
+        len += (code[pc + 1] as usize + 1) * 2; // Jump table of `max_index + 1` offsets
+    }
+    if pc + len > code.len() {
+        return Err(EOFError::TruncatedImmediate(opcode));
+    }
+    Ok(len)
+}
+
+/// Returns the (inputs, outputs) stack effect of the instruction at `pc`,
+/// resolving the immediate-dependent EIP-663 forms. CALLF takes its effect from
+/// the target's entry in `types`, and is stack-neutral if the target is not listed.
+fn stack_effect(code: &[u8], pc: usize, types: &[FunctionType]) -> (i32, i32) {
+    let opcode = code[pc];
+    match opcode {
+        CALLF => {
+            let target = u16::from_be_bytes([code[pc + 1], code[pc + 2]]) as usize;
+            types.get(target).map_or((0, 0), |ft| (ft.inputs as i32, ft.outputs as i32))
+        },
+        DUPN => {
+            // EIP-663: DUPN n copies stack item n (0-based), so the height must exceed n
+            let n = code[pc + 1] as i32;
+            (n + 1, n + 2)
+        },
+        SWAPN => {
+            let n = code[pc + 1] as i32;
+            (n + 2, n + 2)
+        },
+        EXCHANGE => {
+            let n = (code[pc + 1] >> 4) as i32 + 1;
+            let m = (code[pc + 1] & 0x0F) as i32 + 1;
+            (n + m + 1, n + m + 1)
+        },
+        _ => opcode_info(opcode).map_or((0, 0), |info| (info.inputs as i32, info.outputs as i32)),
+    }
+}
+
+/// Returns the unchecked targets of the relative jump at `pc` (of length `len`),
+/// or nothing if the instruction is not RJUMP/RJUMPI/RJUMPV.
+/// Offsets are relative to the instruction following the jump (EIP-4200).
+fn relative_jump_targets(code: &[u8], pc: usize, len: usize) -> Vec<isize> {
+    let next = pc + len;
+    let relative = |offset_pos: usize| {
+        let offset = i16::from_be_bytes([code[offset_pos], code[offset_pos + 1]]);
+        next as isize + offset as isize
+    };
+    match code[pc] {
+        RJUMP | RJUMPI => vec![relative(pc + 1)],
+        RJUMPV => (0..=code[pc + 1] as usize).map(|entry| relative(pc + 2 + entry * 2)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns true if the instruction ends its basic block without falling through.
+fn is_terminating(opcode: u8) -> bool {
+    opcode_info(opcode).is_some_and(|info| info.terminating)
+}
+
+/// Returns the in-section offsets control may reach after the instruction at `pc`
+/// (of length `len`): the fall-through (unless terminating) and any relative jump targets.
+fn successors(code: &[u8], pc: usize, len: usize) -> Vec<usize> {
+    let mut targets = relative_jump_targets(code, pc, len);
+    if !is_terminating(code[pc]) {
+        targets.push((pc + len) as isize);
+    }
+    targets.into_iter()
+        .filter(|&t| t >= 0 && (t as usize) < code.len())
+        .map(|t| t as usize)
+        .collect()
+}
+
+/// Returns how many items must already be on the stack for `code` to run from
+/// offset 0 without underflowing. Walks every path reachable through relative
+/// jumps and fall-through, tracking the lowest height relative to the entry.
+/// Without Type information, CALLF is treated as stack-neutral. Needing more
+/// than `u16::MAX` items fails with `StackUnderflow`.
+pub fn min_initial_stack(code: &[u8]) -> Result<u16, EOFError> {
+    let mut visited = vec![false; code.len()];
+    let mut worklist = vec![(0usize, 0i32)];
+    let mut lowest = 0i32;
+    while let Some((pc, height)) = worklist.pop() {
+        // Only the first height reaching `pc` is followed: EIP-5450 requires every
+        // path merging at an instruction to agree on the height there.
+        if pc >= code.len() || visited[pc] {
+            continue;
+        }
+        visited[pc] = true;
+        let len = instruction_len(code, pc)?;
+        let (inputs, outputs) = stack_effect(code, pc, &[]);
+        lowest = lowest.min(height - inputs);
+        let next_height = height - inputs + outputs;
+        for target in successors(code, pc, len) {
+            worklist.push((target, next_height));
+        }
+    }
+    u16::try_from(-lowest).map_err(|_| EOFError::StackUnderflow)
+}
+
+/// EIP-5450: upper bound on the operand stack height of any function.
+pub const MAX_STACK_HEIGHT: u16 = 1023;
+
+/// EIP-5450 forward stack-height analysis of code section `section`, whose Type
+/// entry is `types[section]`. Heights start at the function's inputs and follow
+/// fall-through and relative jumps; CALLF applies its target's `outputs - inputs`
+/// and requires at least `inputs` items; JUMPF requires its target's inputs and,
+/// for a returning target, matching outputs. Every path reaching an instruction
+/// must imply the same height there. Returns the maximum height reached.
+pub fn validate_stack_heights(code: &[u8], types: &[FunctionType], section: usize) -> Result<u16, EOFError> {
+    let function = types.get(section).ok_or(EOFError::InvalidSectionIndex(section))?;
+    let (_, max_height) = forward_stack_heights(code, function, types)?;
+    Ok(max_height as u16)
+}
+
+/// EIP-5450: runs `validate_stack_heights` and requires the `max_stack_height`
+/// declared in `types[section]` to equal the computed maximum exactly, rejecting
+/// over-declared as well as under-declared bounds.
+pub fn validate_declared_max_stack(code: &[u8], types: &[FunctionType], section: usize) -> Result<(), EOFError> {
+    let computed = validate_stack_heights(code, types, section)?;
+    let declared = types[section].max_stack_height;
+    if declared != computed {
+        return Err(EOFError::DeclaredMaxStackMismatch { declared, computed });
+    }
+    Ok(())
+}
+
+/// Returns the stack height just before the instruction at `offset` of a code section
+/// whose Type entry is `ft`, using the same forward analysis as `validate_stack_heights`.
+/// Without the other Type entries, CALLF is treated as stack-neutral and CALLF/JUMPF
+/// targets are not checked.
+pub fn stack_height_at(code: &[u8], ft: &FunctionType, offset: usize) -> Result<u16, EOFError> {
+    if offset >= code.len() {
+        return Err(EOFError::UnexpectedEndOfInput);
+    }
+    let mut boundary = false;
+    for instr in instructions(code) {
+        let instr = instr?;
+        if instr.offset >= offset {
+            boundary = instr.offset == offset;
+            break;
+        }
+    }
+    if !boundary {
+        return Err(EOFError::JumpToImmediate(offset));
+    }
+    let (heights, _) = forward_stack_heights(code, ft, &[])?;
+    heights[offset].map(|height| height as u16).ok_or(EOFError::UnreachableOffset(offset))
+}
+
+/// Records the height at every instruction reachable from offset 0 of a section
+/// typed `function`, and the maximum height reached. An empty `types` skips the
+/// CALLF/JUMPF target checks.
+fn forward_stack_heights(code: &[u8], function: &FunctionType, types: &[FunctionType]) -> Result<(Vec<Option<i32>>, i32), EOFError> {
+    let mut heights: Vec<Option<i32>> = vec![None; code.len()];
+    let mut worklist = vec![(0usize, function.inputs as i32)];
+    let mut max_height = function.inputs as i32;
+    while let Some((pc, height)) = worklist.pop() {
+        if pc >= code.len() {
+            continue;
+        }
+        match heights[pc] {
+            Some(recorded) if recorded != height => return Err(EOFError::StackHeightMismatch(pc)),
+            Some(_) => continue,
+            None => heights[pc] = Some(height),
+        }
+        let len = instruction_len(code, pc)?;
+        if code[pc] == CALLF && !types.is_empty() {
+            let target = read_u16_be(code, pc + 1)? as usize;
+            if target >= types.len() {
+                return Err(EOFError::InvalidSectionIndex(target));
+            }
+        }
+        // EIP-6206: JUMPF is a terminating tail call. The target consumes its inputs
+        // from the current frame and, if it returns, returns on this function's behalf.
+        if code[pc] == JUMPF && !types.is_empty() {
+            let target = read_u16_be(code, pc + 1)?;
+            let target_type = types.get(target as usize).ok_or(EOFError::InvalidSectionIndex(target as usize))?;
+            if height < target_type.inputs as i32 {
+                return Err(EOFError::StackUnderflow);
+            }
+            if !target_type.is_non_returning() && target_type.outputs != function.outputs {
+                return Err(EOFError::JumpfOutputMismatch(target));
+            }
+        }
+        let (inputs, outputs) = stack_effect(code, pc, types);
+        if height < inputs {
+            return Err(EOFError::StackUnderflow);
+        }
+        let next_height = height - inputs + outputs;
+        if next_height > MAX_STACK_HEIGHT as i32 {
+            return Err(EOFError::StackOverflow);
+        }
+        max_height = max_height.max(next_height);
+        for target in successors(code, pc, len) {
+            worklist.push((target, next_height));
+        }
+    }
+    Ok((heights, max_height))
+}
+
+/// Returns the opcodes that can execute starting from the entry of code section 0,
+/// following fall-through, relative jumps and CALLF/JUMPF into other sections.
+/// Bytes in dead code are not included.
+pub fn reachable_opcodes(container: &EOFContainer) -> Result<BTreeSet<u8>, EOFError> {
+    let sections: Vec<&[u8]> = container.code_sections().collect();
+    let mut visited = BTreeSet::new();
+    let mut worklist = vec![(0usize, 0usize)];
+    let mut opcodes = BTreeSet::new();
+    while let Some((section, pc)) = worklist.pop() {
+        let code = *sections.get(section).ok_or(EOFError::InvalidSectionIndex(section))?;
+        if pc >= code.len() || !visited.insert((section, pc)) {
+            continue;
+        }
+        opcodes.insert(code[pc]);
+        let len = instruction_len(code, pc)?;
+        if code[pc] == CALLF || code[pc] == JUMPF {
+            worklist.push((read_u16_be(code, pc + 1)? as usize, 0));
+        }
+        worklist.extend(successors(code, pc, len).into_iter().map(|target| (section, target)));
+    }
+    Ok(opcodes)
+}
+
+/// EIP-4750: maximum number of return frames on the call stack.
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+/// A return frame pushed by CALLF and popped by RETF.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ReturnFrame {
+    pub section: usize,    // Code section index of the caller
+    pub return_pc: usize,  // Offset of the instruction following the CALLF
+    pub stack_base: usize, // Stack depth at which the caller's frame starts
+}
+
+/// A 256-bit EVM stack word, big-endian.
+pub type Word = [u8; 32];
+
+/// Returns a word holding `val` in its least significant byte.
+pub fn word_from_u8(val: u8) -> Word {
+    let mut word = [0u8; 32];
+    word[31] = val;
+    word
+}
+
+/// EIP-4200: every RJUMP/RJUMPI/RJUMPV target must be an instruction boundary
+/// within the same code section. Offsets are bounded by the section's own length,
+/// so a jump can never reach into a neighbouring section of the container.
+fn validate_relative_jumps(code: &[u8]) -> Result<(), EOFError> {
+    let instrs: Vec<Instruction> = instructions(code).collect::<Result<_, _>>()?;
+    let boundaries: BTreeSet<usize> = instrs.iter().map(|instr| instr.offset).collect();
+    for instr in &instrs {
+        for target in relative_jump_targets(code, instr.offset, 1 + instr.immediate.len()) {
+            if target < 0 || target as usize >= code.len() {
+                return Err(EOFError::InvalidJumpDestination(instr.offset));
+            }
+            if !boundaries.contains(&(target as usize)) {
+                return Err(EOFError::JumpToImmediate(target as usize));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the offsets targeted by the RJUMP/RJUMPI/RJUMPV instructions of `code`.
+/// A target outside the section fails with `InvalidJumpDestination` at the jump.
+pub fn jump_targets(code: &[u8]) -> Result<BTreeSet<usize>, EOFError> {
+    let mut targets = BTreeSet::new();
+    for instr in instructions(code) {
+        let instr = instr?;
+        for target in relative_jump_targets(code, instr.offset, 1 + instr.immediate.len()) {
+            if target < 0 || target as usize >= code.len() {
+                return Err(EOFError::InvalidJumpDestination(instr.offset));
+            }
+            targets.insert(target as usize);
+        }
+    }
+    Ok(targets)
+}
+
+/// Control-flow graph of a single code section.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Cfg {
+    /// Basic blocks as `(start offset, end offset)`, with `end` exclusive, in code order.
+    pub blocks: Vec<(usize, usize)>,
+    /// Directed edges as `(from block index, to block index)`.
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// A basic block of a code section, as returned by `EOFContainer::basic_blocks`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize, // Exclusive
+    pub reachable: bool, // Reachable from the section entry
+    pub successors: Vec<usize>, // Indices of successor blocks
+}
+
+/// Builds the control-flow graph of a code section. Blocks start at offset 0,
+/// at every RJUMP/RJUMPI/RJUMPV target and after every jump or terminating
+/// instruction; edges follow jump targets and fall-through.
+pub fn build_cfg(code: &[u8]) -> Result<Cfg, EOFError> {
+    let instrs: Vec<Instruction> = instructions(code).collect::<Result<_, _>>()?;
+    validate_relative_jumps(code)?;
+
+    let mut leaders = BTreeSet::new();
+    if !code.is_empty() {
+        leaders.insert(0);
+    }
+    for instr in &instrs {
+        let len = 1 + instr.immediate.len();
+        let targets = relative_jump_targets(code, instr.offset, len);
+        leaders.extend(targets.iter().map(|&target| target as usize));
+        let next = instr.offset + len;
+        if (!targets.is_empty() || is_terminating(instr.opcode)) && next < code.len() {
+            leaders.insert(next);
+        }
+    }
+
+    let starts: Vec<usize> = leaders.into_iter().collect();
+    let blocks: Vec<(usize, usize)> = starts.iter().enumerate()
+        .map(|(i, &start)| (start, starts.get(i + 1).copied().unwrap_or(code.len())))
+        .collect();
+    let block_of = |offset: usize| starts.partition_point(|&start| start <= offset) - 1;
+
+    let mut edges = Vec::new();
+    for (from, &(_, end)) in blocks.iter().enumerate() {
+        let last = instrs.iter().rev().find(|instr| instr.offset < end).ok_or(EOFError::UnexpectedEndOfInput)?;
+        for target in successors(code, last.offset, 1 + last.immediate.len()) {
+            let edge = (from, block_of(target));
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    Ok(Cfg { blocks, edges })
+}
+
+/// Statically known outcome of a conditional jump.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BranchDirection {
+    Taken,
+    FallThrough,
+}
+
+/// An RJUMPI whose condition is a constant pushed immediately before it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ConstantBranch {
+    pub offset: usize, // Offset of the PUSH starting the `PUSH <const> RJUMPI` pattern
+    pub direction: BranchDirection,
+}
+
+/// Finds `PUSH0 RJUMPI` (always falls through) and `PUSHn <const> RJUMPI`
+/// patterns so a tool can rewrite them into a plain RJUMP or remove them.
+/// RJUMPIs that are themselves jump targets are skipped, since other paths
+/// may reach them with a different condition. Analysis only; `code` is not modified.
+pub fn fold_constant_branches(code: &[u8]) -> Vec<ConstantBranch> {
+    let instrs: Vec<Instruction> = instructions(code).map_while(Result::ok).collect();
+    let targets: BTreeSet<isize> = instrs.iter()
+        .flat_map(|instr| relative_jump_targets(code, instr.offset, 1 + instr.immediate.len()))
+        .collect();
+    instrs.windows(2)
+        .filter(|pair| pair[1].opcode == RJUMPI && !targets.contains(&(pair[1].offset as isize)))
+        .filter_map(|pair| {
+            let push = &pair[0];
+            let direction = match push.opcode {
+                PUSH0 => BranchDirection::FallThrough,
+                PUSH1..=PUSH32 if push.immediate.iter().any(|&b| b != 0) => BranchDirection::Taken,
+                PUSH1..=PUSH32 => BranchDirection::FallThrough,
+                _ => return None,
+            };
+            Some(ConstantBranch { offset: push.offset, direction })
+        })
+        .collect()
+}
+
+/// Returns the offsets of RJUMP instructions that jump to themselves
+/// (`RJUMP 0xFF 0xFD`), a trivial infinite loop. Longer cycles are not detected.
+pub fn find_trivial_infinite_loops(code: &[u8]) -> Vec<usize> {
+    instructions(code)
+        .map_while(Result::ok)
+        .filter(|instr| instr.opcode == RJUMP)
+        .filter(|instr| relative_jump_targets(code, instr.offset, 1 + instr.immediate.len()) == [instr.offset as isize])
+        .map(|instr| instr.offset)
+        .collect()
+}
+
+/// Returns the offsets of PUSH0..PUSH32 instructions directly followed by a POP,
+/// whose value is discarded unused so the pair can be elided. A POP that is a
+/// jump target is skipped, since other paths reach it with their own value.
+pub fn find_dead_pushes(code: &[u8]) -> Vec<usize> {
+    let instrs: Vec<Instruction> = instructions(code).map_while(Result::ok).collect();
+    let targets: BTreeSet<isize> = instrs.iter()
+        .flat_map(|instr| relative_jump_targets(code, instr.offset, 1 + instr.immediate.len()))
+        .collect();
+    instrs.windows(2)
+        .filter(|pair| (PUSH0..=PUSH32).contains(&pair[0].opcode) && pair[1].opcode == POP)
+        .filter(|pair| !targets.contains(&(pair[1].offset as isize)))
+        .map(|pair| pair[0].offset)
+        .collect()
+}
+
+// Simple stack of 256-bit words for simulation
+pub struct SimulatedStack {
+    items: Vec<Word>,
+    limit: usize, // Maximum number of items; push fails with StackOverflow beyond it
+}
+
+impl Default for SimulatedStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedStack {
+    /// Creates an empty stack with the EVM limit of 1024 items.
+    pub fn new() -> Self {
+        Self::new_with_limit(1024)
+    }
+
+    /// Creates an empty stack holding at most `limit` items.
+    pub fn new_with_limit(limit: usize) -> Self {
+        SimulatedStack { items: Vec::new(), limit }
+    }
+
+    /// Returns the maximum number of items this stack can hold.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Pushes a small value, zero-extended to a full word.
+    pub fn push(&mut self, val: u8) -> Result<(), EOFError> {
+        self.push_word(word_from_u8(val))
+    }
+
+    pub fn push_word(&mut self, word: Word) -> Result<(), EOFError> {
+        if self.items.len() >= self.limit {
+            return Err(EOFError::StackOverflow);
+        }
+        self.items.push(word);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<Word, EOFError> {
+        self.items.pop().ok_or(EOFError::StackUnderflow)
+    }
+
+    /// Returns the word `depth` items below the top (0 = top) without popping it.
+    pub fn peek(&self, depth: usize) -> Result<&[u8], EOFError> {
+        self.items.len()
+            .checked_sub(depth + 1)
+            .map(|index| &self.items[index][..])
+            .ok_or(EOFError::StackUnderflow)
+    }
+
+    /// Returns the number of items currently on the stack.
+    pub fn depth(&self) -> usize {
+        self.items.len()
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Simulates a single step of EOF code execution, focusing on control flow.
+/// This is a simplified prototype, not a full EVM interpreter.
+/// CALLF is stepped over without entering the callee; checking it against the
+/// callee's `max_stack_height` needs the Type entries passed through
+/// `SimulateConfig::function_types` (see `simulate_eof_step_cfg`).
+pub fn simulate_eof_step(
+    code_section: &[u8],
+    pc: &mut usize,
+    stack: &mut SimulatedStack,
+) -> Result<(), EOFError> {
+    simulate_eof_step_cfg(code_section, pc, stack, &SimulateConfig::default())
+}
+
+/// Options for `simulate_eof_step_cfg`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SimulateConfig {
+    /// Fail with `InvalidOpcode` on opcodes missing from `opcode_info` instead of
+    /// stepping over them.
+    pub strict_unknown: bool,
+    /// Instruction start offsets of the code being stepped, precomputed once by
+    /// `SimulateConfig::verifying_boundaries`. When set, fail with `JumpToImmediate`
+    /// if `pc` does not start an instruction (e.g. it points into PUSH data)
+    /// instead of decoding the immediate as an opcode.
+    pub verify_boundary: Option<BTreeSet<usize>>,
+    /// Type entries of the container the code belongs to. CALLF to an entry listed
+    /// here fails with `StackOverflow` if the callee could grow the stack past
+    /// `stack.limit()`; with no entries CALLF is not checked.
+    pub function_types: Vec<FunctionType>,
+}
+
+impl SimulateConfig {
+    /// Returns a config that checks every step of `code` against its instruction
+    /// boundaries, decoding `code` once up front rather than on each step.
+    pub fn verifying_boundaries(code: &[u8]) -> Self {
+        SimulateConfig { verify_boundary: Some(instruction_boundaries(code)), ..SimulateConfig::default() }
+    }
+}
+
+/// Returns the offsets at which instructions of `code` start, stopping at the
+/// first truncated instruction.
+fn instruction_boundaries(code: &[u8]) -> BTreeSet<usize> {
+    instructions(code).map_while(Result::ok).map(|instr| instr.offset).collect()
+}
+
+/// Like `simulate_eof_step`, with the behavior selected by `config`.
+pub fn simulate_eof_step_cfg(
+    code_section: &[u8],
+    pc: &mut usize,
+    stack: &mut SimulatedStack,
+    config: &SimulateConfig,
+) -> Result<(), EOFError> {
+    if *pc >= code_section.len() {
+        return Err(EOFError::UnexpectedEndOfInput); // Out of bounds
+    }
+    if config.verify_boundary.as_ref().is_some_and(|boundaries| !boundaries.contains(pc)) {
+        return Err(EOFError::JumpToImmediate(*pc));
+    }
+
+    let opcode = code_section[*pc];
+    match opcode {
+        // --- Existing opcodes (for context, simplified handling) ---
+        PUSH1..=PUSH32 => {
+            let push_size = (opcode - PUSH1 + 1) as usize;
+            if *pc + 1 + push_size > code_section.len() {
+                return Err(EOFError::TruncatedPushData); // Should be caught by validation
+            }
+            // Push the immediate data, right-aligned in a 256-bit word
+            let mut word = [0u8; 32];
+            word[32 - push_size..].copy_from_slice(&code_section[*pc + 1..*pc + 1 + push_size]);
+            stack.push_word(word)?;
+            *pc += push_size + 1;
+        },
+        // A simple opcode that consumes one stack item and does nothing
+        0x01 => { // ADD for example
+            stack.pop()?; // Consume two, push one. Simplified.
+            stack.pop()?;
+            stack.push(0)?; // Dummy result
+            *pc += 1;
+        },
+        GAS => {
+            stack.push(0)?; // Dummy remaining gas; gas is not metered in this prototype
+            *pc += 1;
+        },
+        // --- EOF Jumps (EIP-4200) ---
+        RJUMP => {
+            let offset = read_u16_be(code_section, *pc + 1)? as i16; // 2-byte signed immediate
+            *pc = (*pc as isize + 3 + offset as isize) as usize;
+        },
+        RJUMPI => {
+            // Read the immediate before popping so a truncated RJUMPI leaves the stack intact
+            let offset = read_u16_be(code_section, *pc + 1)? as i16; // 2-byte signed immediate
+            let condition = stack.pop()?;
+
+            if condition.iter().any(|&b| b != 0) { // If condition is true (any byte non-zero)
+                *pc = (*pc as isize + 3 + offset as isize) as usize;
+            } else {
+                *pc += 3; // Skip opcode and immediate
+            }
+        },
+        RJUMPV => {
+            let max_index = *code_section.get(*pc + 1).ok_or(EOFError::UnexpectedEndOfInput)? as usize;
+            let next = *pc + 2 + (max_index + 1) * 2; // opcode + max_index + jump table
+            if next > code_section.len() {
+                return Err(EOFError::UnexpectedEndOfInput);
+            }
+            let selector = stack.pop()?;
+            // Any non-zero high byte puts the selector out of range, so only the low byte can index the table
+            let in_range = selector[..31].iter().all(|&b| b == 0) && (selector[31] as usize) <= max_index;
+            if in_range {
+                let offset = read_u16_be(code_section, *pc + 2 + selector[31] as usize * 2)? as i16;
+                *pc = (next as isize + offset as isize) as usize;
+            } else {
+                *pc = next; // Out-of-range selector falls through past the table
+            }
+        },
+        _ if config.strict_unknown && opcode_info(opcode).is_none() => {
+            return Err(EOFError::InvalidOpcode(opcode));
+        },
+        // --- EOF Functions (EIP-4750): a single code section cannot enter the callee ---
+        CALLF => {
+            let target = read_u16_be(code_section, *pc + 1)? as usize;
+            if let Some(callee) = config.function_types.get(target) {
+                check_callee_stack(stack, callee)?;
+            }
+            *pc += 3;
+        },
+        // --- Default: unknown opcode, just advance PC ---
+        _ => *pc += 1,
+    }
+    Ok(())
+}
+
+/// EIP-4750: CALLF fails if `callee` could grow the stack past `stack.limit()`,
+/// i.e. if `height + max_stack_height - inputs > limit`.
+fn check_callee_stack(stack: &SimulatedStack, callee: &FunctionType) -> Result<(), EOFError> {
+    let callee_growth = callee.max_stack_height.saturating_sub(callee.inputs as u16) as usize;
+    if stack.depth() + callee_growth > stack.limit() {
+        return Err(EOFError::StackOverflow);
+    }
+    Ok(())
+}
+
+/// Runs `simulate_eof_step` from offset 0 until a terminating instruction
+/// (STOP, RETURN, REVERT, RETF or INVALID) is reached, returning the number of
+/// steps executed including the terminator. Backward RJUMPs can loop forever,
+/// so the run fails with `StepLimitExceeded` after `max_steps` steps.
+pub fn simulate_eof_run(code: &[u8], stack: &mut SimulatedStack, max_steps: usize) -> Result<usize, EOFError> {
+    run_with_stack_limit(code, stack, max_steps, None)
+}
+
+/// Like `simulate_eof_run` for a function typed `ft`: `stack` holds the function's
+/// inputs on entry, and exceeding the declared `max_stack_height` fails with
+/// `StackOverflow` even below the global 1024-item limit.
+pub fn simulate_eof_function_run(code: &[u8], ft: &FunctionType, stack: &mut SimulatedStack, max_steps: usize) -> Result<usize, EOFError> {
+    run_with_stack_limit(code, stack, max_steps, Some(ft.max_stack_height as usize))
+}
+
+fn run_with_stack_limit(code: &[u8], stack: &mut SimulatedStack, max_steps: usize, max_height: Option<usize>) -> Result<usize, EOFError> {
+    let mut pc = 0;
+    let mut steps = 0;
+    while steps < max_steps {
+        let opcode = *code.get(pc).ok_or(EOFError::UnexpectedEndOfInput)?;
+        steps += 1;
+        if matches!(opcode, STOP | RETURN | REVERT | RETF | INVALID) {
+            return Ok(steps);
+        }
+        simulate_eof_step(code, &mut pc, stack)?;
+        if max_height.is_some_and(|max| stack.depth() > max) {
+            return Err(EOFError::StackOverflow);
+        }
+    }
+    Err(EOFError::StepLimitExceeded)
+}
+
+/// One executed step of a simulation trace, recorded before the instruction runs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub opcode: u8,
+    pub stack_height: usize,
+}
+
+/// Like `simulate_eof_run`, but records every executed step (including the
+/// terminator) for differential testing against other EVM implementations.
+pub fn simulate_eof_trace(code: &[u8], stack: &mut SimulatedStack, max_steps: usize) -> Result<Vec<TraceEntry>, EOFError> {
+    let mut pc = 0;
+    let mut trace = Vec::new();
+    while trace.len() < max_steps {
+        let opcode = *code.get(pc).ok_or(EOFError::UnexpectedEndOfInput)?;
+        trace.push(TraceEntry { pc, opcode, stack_height: stack.depth() });
+        if matches!(opcode, STOP | RETURN | REVERT | RETF | INVALID) {
+            return Ok(trace);
+        }
+        simulate_eof_step(code, &mut pc, stack)?;
+    }
+    Err(EOFError::StepLimitExceeded)
+}
+
+/// Like `simulate_eof_run`, but starts at code section 0 of `container` and
+/// follows CALLF/RETF between code sections using a simulated call stack of
+/// at most `MAX_CALL_DEPTH` frames. RETF with an empty call stack terminates the run.
+/// CALLF is checked against the callee's Type entry as in `simulate_eof_step_cfg`.
+/// As in `simulate_eof_function_run`, every frame fails with `StackOverflow` once
+/// it exceeds its declared `max_stack_height`, counting the function's inputs;
+/// the entry frame starts at the bottom of `stack`.
+pub fn simulate_eof_container_run(container: &EOFContainer, stack: &mut SimulatedStack, max_steps: usize) -> Result<usize, EOFError> {
+    let code_sections: Vec<&[u8]> = container.code_sections().collect();
+    let types = container.function_types()?;
+    let mut call_stack: Vec<ReturnFrame> = Vec::new();
+    let mut section = 0;
+    let mut stack_base = 0;
+    let mut pc = 0;
+    let mut steps = 0;
+    while steps < max_steps {
+        let code = *code_sections.get(section).ok_or(EOFError::InvalidSectionIndex(section))?;
+        let opcode = *code.get(pc).ok_or(EOFError::UnexpectedEndOfInput)?;
+        steps += 1;
+        match opcode {
+            STOP | RETURN | REVERT | INVALID => return Ok(steps),
+            CALLF => {
+                let target = read_u16_be(code, pc + 1)? as usize;
+                if call_stack.len() >= MAX_CALL_DEPTH {
+                    return Err(EOFError::CallStackOverflow);
+                }
+                let mut callee_base = stack.depth();
+                if let Some(callee) = types.get(target) {
+                    check_callee_stack(stack, callee)?;
+                    callee_base = callee_base.saturating_sub(callee.inputs as usize);
+                }
+                call_stack.push(ReturnFrame { section, return_pc: pc + 3, stack_base });
+                section = target;
+                stack_base = callee_base;
+                pc = 0;
+            },
+            RETF => match call_stack.pop() {
+                Some(frame) => {
+                    section = frame.section;
+                    stack_base = frame.stack_base;
+                    pc = frame.return_pc;
+                },
+                None => return Ok(steps),
+            },
+            _ => {
+                simulate_eof_step(code, &mut pc, stack)?;
+                let max_height = types.get(section).map_or(usize::MAX, |ft| ft.max_stack_height as usize);
+                if stack.depth().saturating_sub(stack_base) > max_height {
+                    return Err(EOFError::StackOverflow);
+                }
+            },
+        }
+    }
+    Err(EOFError::StepLimitExceeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- Helper function to create a minimal valid EOF container ---
+    fn create_valid_eof_bytecode(code_sections: Vec<Vec<u8>>, data_section: Option<Vec<u8>>) -> Vec<u8> {
+        create_eof_bytecode_with_containers(code_sections, vec![], data_section)
+    }
+
+    // --- Helper function to create an initcode container deploying a STOP-only runtime container ---
+    fn create_initcode_bytecode() -> Vec<u8> {
+        let runtime = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime], None)
+    }
+
+    // --- Helper function to create an EOF container with nested Container sections ---
+    fn create_eof_bytecode_with_containers(code_sections: Vec<Vec<u8>>, containers: Vec<Vec<u8>>, data_section: Option<Vec<u8>>) -> Vec<u8> {
+        let mut bytecode = Vec::new();
+        bytecode.extend_from_slice(&EOF_MAGIC.to_be_bytes()); // Magic
+        bytecode.push(EOF_VERSION); // Version
+
+        // Dummy Type section (size depends on number of code sections)
+        let type_size = (code_sections.len() * 4) as u16; // Assuming 4 bytes per function entry
+        bytecode.push(SectionKind::Type as u8);
+        bytecode.extend_from_slice(&type_size.to_be_bytes());
+
+        // Code sections
+        for code in &code_sections {
+            bytecode.push(SectionKind::Code as u8);
+            bytecode.extend_from_slice(&(code.len() as u16).to_be_bytes());
+        }
+
+        // Container sections
+        for subcontainer in &containers {
+            bytecode.push(SectionKind::Container as u8);
+            bytecode.extend_from_slice(&(subcontainer.len() as u16).to_be_bytes());
+        }
+
+        // Data section
+        if let Some(data) = &data_section {
+            bytecode.push(SectionKind::Data as u8);
+            bytecode.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        }
+
+        bytecode.push(0x00); // Terminator
+
+        // Section contents
+        bytecode.extend(vec![0x00; type_size as usize]); // Dummy type content (e.g., input/output counts)
+        for code in code_sections {
+            bytecode.extend(code);
+        }
+        for subcontainer in containers {
+            bytecode.extend(subcontainer);
+        }
+        if let Some(data) = data_section {
+            bytecode.extend(data);
+        }
+        bytecode
+    }
+
+    // --- Helper function to overwrite the Type section entries of an encoded container ---
+    fn with_function_types(bytecode: Vec<u8>, types: &[FunctionType]) -> Vec<u8> {
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        container.sections[0] = types.iter().flat_map(FunctionType::to_bytes).collect();
+        container.to_bytes()
+    }
+
+    // --- Parse tests (from previous version, ensuring they still pass) ---
+    #[test]
+    fn test_parse_simple_eof_container() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![0x01, 0x02]], Some(vec![0x03, 0x04]));
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.header.version, 0x01);
+        assert_eq!(container.sections.len(), 3);
+        // Section 0 is Type, Section 1 is Code, Section 2 is Data
+        assert_eq!(container.sections[1], vec![0x01, 0x02]); // Code section
+        assert_eq!(container.sections[2], vec![0x03, 0x04]); // Data section
+    }
+
+    #[test]
+    fn test_invalid_magic() {
+        let bytecode = vec![0xDE, 0xAD, 0x01, 0x00];
+        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::NotEOF));
+    }
+
+    #[test]
+    fn test_magic_bytes_checked_separately() {
+        let valid = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let mut wrong_first = valid.clone();
+        wrong_first[0] = 0xEE;
+        assert_eq!(parse_eof_container(&wrong_first), Err(EOFError::NotEOF));
+        let mut wrong_second = valid.clone();
+        wrong_second[1] = 0x01;
+        assert_eq!(parse_eof_container(&wrong_second), Err(EOFError::InvalidMagic));
+
+        assert_eq!(parse_eof_container(&[]), Err(EOFError::UnexpectedEndOfInput));
+        assert_eq!(parse_eof_container(&[0x60]), Err(EOFError::NotEOF));
+        assert_eq!(parse_eof_container(&[0xEF]), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        let bytecode = vec![0xEF, 0x00, 0x02, 0x00];
+        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::InvalidVersion(0x02)));
+    }
+
+    #[test]
+    fn test_headers_without_terminator() {
+        // Magic, version, Type header (size 4) and Code header (size 1), then end of input
+        let bytecode = vec![0xEF, 0x00, 0x01, 0x01, 0x00, 0x04, 0x02, 0x00, 0x01];
+        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::MissingTerminator));
+    }
+
+    #[test]
+    fn test_no_headers_is_unexpected_end() {
+        let bytecode = vec![0xEF, 0x00, 0x01];
+        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn test_duplicate_data_section_reports_index() {
+        let mut bytecode = vec![0xEF, 0x00, EOF_VERSION];
+        bytecode.extend([SectionKind::Type as u8, 0x00, 0x04]);   // Header 0
+        bytecode.extend([SectionKind::Code as u8, 0x00, 0x01]);   // Header 1
+        bytecode.extend([SectionKind::Data as u8, 0x00, 0x01]);   // Header 2
+        bytecode.extend([SectionKind::Data as u8, 0x00, 0x01]);   // Header 3 (duplicate)
+        bytecode.push(0x00);
+        assert_eq!(
+            parse_eof_container(&bytecode),
+            Err(EOFError::DuplicateSection { kind: SectionKind::Data, at_index: 3 })
+        );
+    }
+
+    #[test]
+    fn test_declared_size_budget() {
+        // Four 64KB code sections are declared, but no bodies follow
+        let mut bytecode = vec![0xEF, 0x00, EOF_VERSION, SectionKind::Type as u8, 0x00, 0x10];
+        for _ in 0..4 {
+            bytecode.extend([SectionKind::Code as u8, 0xFF, 0xFF]);
+        }
+        bytecode.push(0x00);
+        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::ContainerTooLarge));
+    }
+
+    #[test]
+    fn test_strict_parse_rejects_truncated_push() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH2, 0x00]], None);
+        assert!(parse_eof_container(&bytecode).is_ok());
+        assert_eq!(parse_eof_container_strict(&bytecode), Err(EOFError::TruncatedPushData));
+    }
+
+    #[test]
+    fn test_strict_parse_rejects_truncated_immediate() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP, RJUMP, 0x00]], None);
+        assert_eq!(parse_eof_container_strict(&bytecode), Err(EOFError::TruncatedImmediate(RJUMP)));
+        let valid = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None);
+        assert_eq!(parse_eof_container_strict(&valid), parse_eof_container(&valid));
+    }
+    #[test]
+    fn test_parse_eof_header_without_bodies() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP], vec![STOP]], Some(vec![0xAA]));
+        let container = parse_eof_container(&bytecode).unwrap();
+        // Only the header bytes are supplied
+        let header_only = &bytecode[..container.header.encoded_len()];
+        assert_eq!(parse_eof_header(header_only), Ok(container.header));
+        assert_eq!(parse_eof_container(header_only), Err(EOFError::UnexpectedEndOfInput));
+
+        let mut bad_magic = header_only.to_vec();
+        bad_magic[1] = 0x01;
+        assert_eq!(parse_eof_header(&bad_magic), Err(EOFError::InvalidMagic));
+    }
+
+    #[test]
+    fn test_version_zero_is_invalid_version() {
+        // A 0x00 version byte is reported as such, never mistaken for the header terminator
+        let mut bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        bytecode[2] = 0x00;
+        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::InvalidVersion(0)));
+        assert_eq!(parse_eof_container(&[0xEF, 0x00, 0x00]), Err(EOFError::InvalidVersion(0)));
+        assert_eq!(parse_eof_container(&[0xEF, 0x00, 0x00, 0x00]), Err(EOFError::InvalidVersion(0)));
+        assert_eq!(parse_eof_header(&[0xEF, 0x00, 0x00]), Err(EOFError::InvalidVersion(0)));
+        assert_eq!(parse_eof_container(&[0xEF, 0x00]), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn test_version_read_from_index_2() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        assert_eq!(&bytecode[..3], &[0xEF, 0x00, 0x01]);
+        assert_eq!(parse_eof_header(&bytecode).unwrap().version, 1);
+        assert_eq!(parse_eof_container(&bytecode).unwrap().header.version, 1);
+
+        // Reading index 1 (the 0x00 magic byte) would report version 0 instead
+        let mut bytecode = bytecode;
+        bytecode[2] = 0x07;
+        assert_eq!(parse_eof_header(&bytecode), Err(EOFError::InvalidVersion(0x07)));
+    }
+
+    #[test]
+    fn test_parse_eof_container_at_reports_offset() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP]], None);
+        assert!(parse_eof_container_at(&bytecode).is_ok());
+
+        // Drop the last two bytes of the code body: parsing stops at the end of input
+        let truncated = &bytecode[..bytecode.len() - 2];
+        assert_eq!(parse_eof_container_at(truncated), Err((EOFError::UnexpectedEndOfInput, truncated.len())));
+
+        let mut stray = bytecode.clone();
+        stray.push(0x00);
+        assert_eq!(parse_eof_container_at(&stray), Err((EOFError::SectionSizeMismatch, bytecode.len())));
+        assert_eq!(parse_eof_container_at(&[0xEF, 0x00, 0x02]), Err((EOFError::InvalidVersion(2), 2)));
+        assert_eq!(parse_eof_container_at(&[0xEF, 0x00, 0x01, 0x09, 0x00, 0x01]), Err((EOFError::InvalidSectionKind(0x09), 3)));
+    }
+
+    #[test]
+    fn test_parse_eof_container_ref_borrows_input() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP], vec![RETF]], Some(vec![0xAA, 0xBB]));
+        let borrowed = parse_eof_container_ref(&bytecode).unwrap();
+        let input = bytecode.as_ptr_range();
+        for body in &borrowed.sections {
+            assert!(input.contains(&body.as_ptr()));
+        }
+        assert_eq!(borrowed.sections.last().unwrap().as_ptr_range().end, input.end);
+        assert_eq!(borrowed.to_owned(), parse_eof_container(&bytecode).unwrap());
+        assert_eq!(parse_eof_container_ref(&bytecode[..bytecode.len() - 1]), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    // --- End Parse tests ---
+
+    // --- EIP-3670 Validation Tests ---
+
+    #[test]
+    fn test_validate_valid_eof() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, 0x01]], None); // PUSH1 01, PUSH1 02, ADD
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validate_eof_container(&container).is_ok());
+    }
+
+    #[test]
+    fn test_validate_invalid_opcode() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, INVALID]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(INVALID)));
+    }
+
+    #[test]
+    fn test_validate_selfdestruct_forbidden() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, SELFDESTRUCT]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(SELFDESTRUCT)));
+    }
+
+    #[test]
+    fn test_validate_jump_forbidden() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, JUMP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(JUMP)));
+    }
+
+    #[test]
+    fn test_validate_jumpi_forbidden() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, JUMPI]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(JUMPI)));
+    }
+
+    #[test]
+    fn test_validate_pc_forbidden() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PC]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(PC)));
+    }
+
+    #[test]
+    fn test_validate_truncated_push_data() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH2]], None); // PUSH2 needs 2 bytes, only 1 provided
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::TruncatedPushData));
+    }
+
+    #[test]
+    fn test_validate_empty_code_section() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::SectionSizeMismatch));
+    }
+
+    #[test]
+    fn test_validate_type_section_size_mismatch() {
+        let mut bytecode = Vec::new();
+        bytecode.extend_from_slice(&EOF_MAGIC.to_be_bytes()); // Magic
+        bytecode.push(EOF_VERSION); // Version
+        bytecode.push(SectionKind::Type as u8);
+        bytecode.extend_from_slice(&2_u16.to_be_bytes()); // Type size 2, but 1 code section expects 4
+        bytecode.push(SectionKind::Code as u8);
+        bytecode.extend_from_slice(&1_u16.to_be_bytes());
+        bytecode.push(0x00); // Terminator
+        bytecode.extend(vec![0x00, 0x00]); // Dummy type content (size 2)
+        bytecode.extend(vec![0x01]); // Code content
+
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::TypeSectionSizeNotMultipleOf4(2)));
+    }
+
+    #[test]
+    fn test_validate_code_section_count_mismatch() {
+        let mut bytecode = Vec::new();
+        bytecode.extend_from_slice(&EOF_MAGIC.to_be_bytes()); // Magic
+        bytecode.push(EOF_VERSION); // Version
+        bytecode.push(SectionKind::Type as u8);
+        bytecode.extend_from_slice(&8_u16.to_be_bytes()); // Type size 8, but only 1 code section
+        bytecode.push(SectionKind::Code as u8);
+        bytecode.extend_from_slice(&1_u16.to_be_bytes());
+        bytecode.push(0x00); // Terminator
+        bytecode.extend(vec![0x00; 8]); // Dummy type content (size 8)
+        bytecode.extend(vec![0x01]); // Code content
+
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::MalformedSectionHeader));
+    }
+
+    // --- EIP-7480 Data Section Tests ---
+
+    #[test]
+    fn test_validate_dataloadn_in_bounds() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![DATALOADN, 0x00, 0x00]], Some(vec![0x00; 32]));
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validate_eof_container(&container).is_ok());
+    }
+
+    #[test]
+    fn test_validate_dataloadn_within_declared_but_not_present_data() {
+        // Data declared as 64 bytes, but only 4 are present (the rest is appended at deploy time)
+        let bytecode = create_valid_eof_bytecode(vec![vec![DATALOADN, 0x00, 0x20]], Some(vec![0x00; 64]));
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        container.sections[2].truncate(4);
+        assert!(validate_eof_container(&container).is_ok()); // 0x20 + 32 == 64, within declared size
+    }
+
+    #[test]
+    fn test_validate_dataloadn_beyond_declared_data() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![DATALOADN, 0x00, 0x21]], Some(vec![0x00; 64]));
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        container.sections[2].truncate(4);
+        assert_eq!(validate_eof_container(&container), Err(EOFError::DataloadnOutOfBounds(0x21)));
+    }
+
+    #[test]
+    fn test_validate_dataloadn_truncated_immediate() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![DATALOADN, 0x00]], Some(vec![0x00; 32]));
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::TruncatedImmediate(DATALOADN)));
+    }
+
+    // --- EIP-7620 Creation Tests ---
+
+    #[test]
+    fn test_validate_create_forbidden() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x00, CREATE]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::LegacyCreateForbidden(CREATE)));
+    }
+
+    #[test]
+    fn test_validate_create2_forbidden() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x00, CREATE2]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::LegacyCreateForbidden(CREATE2)));
+    }
+
+    #[test]
+    fn test_validate_legacy_create_allowed_by_config() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x00, CREATE]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        let config = ValidationConfig { forbid_legacy_create: false, ..ValidationConfig::default() };
+        assert!(validate_eof_container_with_config(&container, &config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_eofcreate_accepted() {
+        let code = vec![PUSH1, 0x00, EOFCREATE, 0x00];
+        let bytecode = create_eof_bytecode_with_containers(vec![code.clone()], vec![create_initcode_bytecode()], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validate_eof_container(&container).is_ok());
+
+        // EIP-7620: the container index must name an existing subcontainer
+        let dangling = parse_eof_container(&create_valid_eof_bytecode(vec![code], None)).unwrap();
+        assert_eq!(validate_eof_container(&dangling), Err(EOFError::InvalidSectionIndex(0)));
+        let past_end = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x05, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container(&past_end), Err(EOFError::InvalidSectionIndex(5)));
+    }
+
+    // --- Subcontainer Validation Tests ---
+
+    #[test]
+    fn test_validate_valid_subcontainer() {
+        let sub = create_initcode_bytecode();
+        let bytecode = create_eof_bytecode_with_containers(vec![vec![PUSH1, 0x00, EOFCREATE, 0x00]], vec![sub], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validate_eof_container(&container).is_ok());
+    }
+
+    #[test]
+    fn test_validate_forbidden_opcode_in_subcontainer() {
+        let sub = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, SELFDESTRUCT]], None);
+        let bytecode = create_eof_bytecode_with_containers(vec![vec![PUSH1, 0x00, EOFCREATE, 0x00]], vec![sub], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(SELFDESTRUCT)));
+    }
+
+    #[test]
+    fn test_validate_jump_in_nested_subcontainer() {
+        let inner = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, JUMP]], None);
+        let middle = create_eof_bytecode_with_containers(vec![vec![PUSH1, 0x00, EOFCREATE, 0x00]], vec![inner], None);
+        let bytecode = create_eof_bytecode_with_containers(vec![vec![PUSH1, 0x00, EOFCREATE, 0x00]], vec![middle], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(JUMP)));
+    }
+
+    // --- Container Helper Tests ---
+
+    #[test]
+    fn test_without_data_ignores_data_differences() {
+        let a = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01]], Some(vec![0x01, 0x02]))).unwrap();
+        let b = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01]], Some(vec![0x03; 8]))).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.without_data(), b.without_data());
+        assert!(a.without_data().header.section_headers.iter().all(|h| h.kind != SectionKind::Data));
+        assert_eq!(a.without_data().sections.len(), 2);
+    }
+
+    // --- Stack Analysis Tests ---
+
+    #[test]
+    fn test_min_initial_stack_two_pops() {
+        assert_eq!(min_initial_stack(&[POP, POP, STOP]), Ok(2));
+    }
+
+    #[test]
+    fn test_min_initial_stack_beyond_u16() {
+        let mut code = vec![POP; u16::MAX as usize + 1];
+        code.push(STOP);
+        assert_eq!(min_initial_stack(&code), Err(EOFError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_min_initial_stack_push_first() {
+        assert_eq!(min_initial_stack(&[PUSH1, 0x01, POP, STOP]), Ok(0));
+    }
+
+    #[test]
+    fn test_min_initial_stack_follows_branches() {
+        // RJUMPI skips over the POPs when taken; the fall-through path needs 1 + 2 items.
+        let code = vec![RJUMPI, 0x00, 0x02, POP, POP, STOP];
+        assert_eq!(min_initial_stack(&code), Ok(3));
+    }
+
+    // --- Instruction Set Expansion Tests ---
+
+    #[test]
+    fn test_simulate_rjump() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        // RJUMP +0 (relative to next instruction) -> jump to opcode at index 3 (immediately after RJUMP)
+        let code = vec![RJUMP, 0x00, 0x00, 0xFF, 0x01]; // RJUMP +0, INVALID, ADD
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 3); // PC should be 3 (0 + 3 + 0)
+        // Next step should execute 0xFF (INVALID, caught by default step)
+        // simulate_eof_step(&code, &mut pc, &mut stack).unwrap_err(); // Should be INVALID if it had error handling for it
+    }
+
+    #[test]
+    fn test_simulate_rjump_backward() {
+        // Opcode at 0, RJUMP at 1 (offset -2).
+        // If `pc` is 1 for RJUMP, then `new_pc = (1 + 3) + (-2) = 2`. It should jump to PUSH1.
+        let code = vec![0x01, RJUMP, 0xFF, 0xFE, PUSH1, 0x01]; // ADD (0), RJUMP (1), <offset bytes> (2,3), PUSH1 (4), 0x01 (5)
+        let mut pc = 1; // RJUMP is at index 1
+        let mut stack = SimulatedStack::new();
+        stack.push(1).unwrap();
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        // New PC should be (1 + 3) + (-2) = 4 - 2 = 2. It should jump to byte 2 (0xFF).
+        assert_eq!(pc, 2); // Corrected calculation: (start of RJUMP opcode + instruction length) + offset
+    }
+
+    #[test]
+    fn test_simulate_rjump_out_of_bounds() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        // RJUMP to a location outside the code
+        let code = vec![RJUMP, 0x7F, 0xFF]; // RJUMP +32767
+        // Current PC=0. Next PC = (0 + 3) + 32767 = 32770. This will be out of bounds.
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap(); // It doesn't error on the step if the target PC is out of bounds, only next step
+        assert_eq!(pc, 32770);
+        // The *next* simulate_eof_step call would then return UnexpectedEndOfInput.
+    }
+
+
+    #[test]
+    fn test_simulate_rjumpi_true() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push(1).unwrap(); // Condition is true
+        // RJUMPI +2
+        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01]; // RJUMPI +2, INVALID, ADD
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        // New PC should be (0 + 3) + 2 = 5.
+        assert_eq!(pc, 5);
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn test_simulate_rjumpi_false() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push(0).unwrap(); // Condition is false
+        // RJUMPI +2
+        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01]; // RJUMPI +2, INVALID, ADD
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        // New PC should just advance by 3 (opcode + 2 immediate)
+        assert_eq!(pc, 3);
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn test_simulate_rjumpi_stack_underflow() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new(); // Empty stack
+        let code = vec![RJUMPI, 0x00, 0x02, 0xFF];
+        assert_eq!(simulate_eof_step(&code, &mut pc, &mut stack), Err(EOFError::StackUnderflow));
+    }
+
+    #[test]
+    fn test_simulate_rjumpi_truncated_keeps_stack() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push(1).unwrap();
+        let code = vec![RJUMPI, 0x00]; // Only one of the two immediate bytes
+        assert_eq!(simulate_eof_step(&code, &mut pc, &mut stack), Err(EOFError::UnexpectedEndOfInput));
+        assert_eq!(stack.len(), 1);
+        assert_eq!(pc, 0);
+    }
+
+    #[test]
+    fn test_simulate_run_until_stop() {
+        let mut stack = SimulatedStack::new();
+        // PUSH1 01, PUSH1 02, ADD, STOP
+        let code = vec![PUSH1, 0x01, PUSH1, 0x02, ADD, STOP];
+        assert_eq!(simulate_eof_run(&code, &mut stack, 100), Ok(4));
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn test_simulate_run_infinite_loop_hits_step_limit() {
+        let mut stack = SimulatedStack::new();
+        let code = vec![RJUMP, 0xFF, 0xFD]; // RJUMP -3: jumps back to itself
+        assert_eq!(simulate_eof_run(&code, &mut stack, 100), Err(EOFError::StepLimitExceeded));
+    }
+
+    #[test]
+    fn test_simulate_callf_retf_round_trip() {
+        let bytecode = create_valid_eof_bytecode(vec![
+            vec![CALLF, 0x00, 0x01, STOP],  // Section 0 calls section 1
+            vec![CALLF, 0x00, 0x02, RETF],  // Section 1 calls section 2
+            vec![PUSH1, 0x01, RETF],        // Section 2 pushes a value and returns
+        ], None);
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        // Each frame holds at most the one value pushed by section 2
+        container.sections[0] = vec![0x00, NON_RETURNING_FUNCTION, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01];
+        let mut stack = SimulatedStack::new();
+        // CALLF, CALLF, PUSH1, RETF, RETF, STOP
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 100), Ok(6));
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn test_simulate_self_recursive_callf_overflows() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x00, RETF]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        let mut stack = SimulatedStack::new();
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 10_000), Err(EOFError::CallStackOverflow));
+    }
+
+    #[test]
+    fn test_simulate_callf_invalid_section() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x05, STOP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        let mut stack = SimulatedStack::new();
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 100), Err(EOFError::InvalidSectionIndex(5)));
+    }
+
+    // --- Initcode / Runtime Tests ---
+
+    #[test]
+    fn test_is_initcode_distinguishes_forms() {
+        let runtime = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None);
+        let initcode = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime.clone()], None);
+        let runtime = parse_eof_container(&runtime).unwrap();
+        let initcode = parse_eof_container(&initcode).unwrap();
+        assert!(!runtime.is_initcode());
+        assert!(initcode.is_initcode());
+        assert!(validate_eof_container(&runtime).is_ok());
+        assert!(validate_eof_container(&initcode).is_ok());
+    }
+
+    #[test]
+    fn test_is_initcode_eofcreate_requires_subcontainers() {
+        let sub = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None);
+        let with_sub = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![sub], None);
+        let without_sub = create_valid_eof_bytecode(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], None);
+        assert!(parse_eof_container(&with_sub).unwrap().is_initcode());
+        assert!(!parse_eof_container(&without_sub).unwrap().is_initcode());
+    }
+
+    #[test]
+    fn test_validate_returncontract_in_runtime() {
+        // The subcontainer deployed by RETURNCONTRACT is runtime code, so it must not itself use RETURNCONTRACT
+        let runtime = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None);
+        let bad_runtime = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime], None);
+        let initcode = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![bad_runtime], None);
+        let container = parse_eof_container(&initcode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::ReturnContractInRuntime));
+    }
+
+    #[test]
+    fn test_header_encoded_len() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.header.section_headers.len(), 2);
+        assert_eq!(container.header.encoded_len(), 3 + 2 * 3 + 1);
+        // The Type section body starts right after the header
+        assert_eq!(bytecode.len(), container.header.encoded_len() + 4 + 1);
+    }
+
+    // --- Removed Opcode Tests ---
+
+    #[test]
+    fn test_validate_codecopy_removed() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, PUSH0, PUSH0, CODECOPY]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::OpcodeRemovedInEOF(CODECOPY)));
+    }
+
+    #[test]
+    fn test_validate_extcodesize_removed() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, EXTCODESIZE]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::OpcodeRemovedInEOF(EXTCODESIZE)));
+    }
+
+    #[test]
+    fn test_validate_removed_opcodes_allowed_by_config() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, EXTCODESIZE]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        let config = ValidationConfig { forbid_removed_opcodes: false, ..ValidationConfig::default() };
+        assert!(validate_eof_container_with_config(&container, &config).is_ok());
+    }
+
+    #[test]
+    fn test_first_error_location() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, INVALID]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(first_error_location(&container), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_first_error_location_second_section() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP], vec![PUSH0, PUSH0, JUMP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(first_error_location(&container), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_first_error_location_valid() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(first_error_location(&container), None);
+    }
+
+    #[test]
+    fn test_validate_structure_ignores_opcodes() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, INVALID]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validate_structure(&container).is_ok());
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(INVALID)));
+    }
+
+    #[test]
+    fn test_validate_structure_rejects_type_mismatch() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        container.header.section_headers[0].size = 8; // Two Type entries for one Code section
+        assert_eq!(validate_structure(&container), Err(EOFError::MalformedSectionHeader));
+    }
+
+    #[test]
+    fn test_simulate_rjumpi_full_word_condition_taken() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push_word(word_from_u8(0x01)).unwrap(); // 0x00..0x00 0x01: non-zero only in the last byte
+        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 5);
+    }
+
+    #[test]
+    fn test_simulate_rjumpi_high_byte_condition_taken() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        let mut condition = [0u8; 32];
+        condition[0] = 0x80; // Non-zero only in the most significant byte
+        stack.push_word(condition).unwrap();
+        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 5);
+    }
+
+    #[test]
+    fn test_simulate_rjumpi_zero_word_falls_through() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push_word([0u8; 32]).unwrap();
+        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 3);
+    }
+
+    #[test]
+    fn test_simulate_push_full_word() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        let code = vec![PUSH2, 0x12, 0x34];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        let word = stack.pop().unwrap();
+        assert_eq!(&word[30..], &[0x12, 0x34]);
+        assert!(word[..30].iter().all(|&b| b == 0));
+    }
+
+    // --- Legacy / EOF Discrimination Tests ---
+
+    #[test]
+    fn test_reject_eof_accepts_legacy() {
+        let legacy = vec![PUSH1, 0x01, PUSH1, 0x02, ADD, STOP];
+        assert!(!is_eof(&legacy));
+        assert_eq!(reject_eof(&legacy), Ok(()));
+    }
+
+    #[test]
+    fn test_reject_eof_rejects_eof() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        assert!(is_eof(&bytecode));
+        assert_eq!(reject_eof(&bytecode), Err(EOFError::UnexpectedEof));
+    }
+
+    // --- Control-Flow Graph Tests ---
+
+    #[test]
+    fn test_cfg_linear_block() {
+        let cfg = build_cfg(&[PUSH1, 0x01, POP, STOP]).unwrap();
+        assert_eq!(cfg.blocks, vec![(0, 4)]);
+        assert!(cfg.edges.is_empty());
+    }
+
+    #[test]
+    fn test_cfg_conditional_branch() {
+        // PUSH0 (0), RJUMPI +1 (1), STOP (4), STOP (5)
+        let cfg = build_cfg(&[PUSH0, RJUMPI, 0x00, 0x01, STOP, STOP]).unwrap();
+        assert_eq!(cfg.blocks, vec![(0, 4), (4, 5), (5, 6)]);
+        assert_eq!(cfg.edges, vec![(0, 2), (0, 1)]); // Taken, then fall-through
+    }
+
+    #[test]
+    fn test_cfg_backward_loop() {
+        // PUSH0 (0), RJUMPI +3 (1), RJUMP -7 (4), STOP (7)
+        let cfg = build_cfg(&[PUSH0, RJUMPI, 0x00, 0x03, RJUMP, 0xFF, 0xF9, STOP]).unwrap();
+        assert_eq!(cfg.blocks, vec![(0, 4), (4, 7), (7, 8)]);
+        assert_eq!(cfg.edges, vec![(0, 2), (0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_cfg_rejects_out_of_range_jump() {
+        assert_eq!(build_cfg(&[RJUMP, 0x00, 0x05]), Err(EOFError::InvalidJumpDestination(0)));
+    }
+
+    #[test]
+    fn test_cfg_rejects_jump_into_immediate() {
+        // RJUMP +1 lands on the immediate byte of PUSH1
+        assert_eq!(build_cfg(&[RJUMP, 0x00, 0x01, PUSH1, 0x01, STOP]), Err(EOFError::JumpToImmediate(4)));
+    }
+
+    // --- Type Section Tests ---
+
+    #[test]
+    fn test_function_types_decoded() {
+        let mut bytecode = create_valid_eof_bytecode(vec![vec![STOP], vec![RETF]], None);
+        let type_body = 3 + 3 * 3 + 1;
+        bytecode[type_body..type_body + 8].copy_from_slice(&[0x00, 0x80, 0x00, 0x02, 0x01, 0x02, 0x01, 0x00]);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.function_types(), Ok(vec![
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 2 },
+            FunctionType { inputs: 1, outputs: 2, max_stack_height: 256 },
+        ]));
+    }
+
+    #[test]
+    fn test_validate_type_body_code_count_mismatch() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        // Header still declares one entry, but the hand-edited body carries two
+        container.sections[0].extend([0x00; 4]);
+        assert_eq!(container.function_types().unwrap().len(), 2);
+        assert_eq!(validate_eof_container(&container), Err(EOFError::MalformedSectionHeader));
+    }
+
+    // --- Conversion Tests ---
+
+    #[test]
+    fn test_try_from_bytes() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], Some(vec![0xAA]));
+        let container: EOFContainer = bytecode.as_slice().try_into().unwrap();
+        assert_eq!(container, parse_eof_container(&bytecode).unwrap());
+        let bad: Result<EOFContainer, _> = [0xDE, 0xAD].as_slice().try_into();
+        assert_eq!(bad, Err(EOFError::NotEOF));
+    }
+
+    #[test]
+    fn test_into_bytes_round_trip() {
+        let bytecode = create_eof_bytecode_with_containers(
+            vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP], vec![RETF]],
+            vec![create_valid_eof_bytecode(vec![vec![STOP]], None)],
+            Some(vec![0x01, 0x02, 0x03]),
+        );
+        let round_trip = Vec::from(EOFContainer::try_from(bytecode.as_slice()).unwrap());
+        assert_eq!(round_trip, bytecode);
+    }
+
+    // --- EIP-6206 Non-Returning Function Tests ---
+
+    #[test]
+    fn test_validate_callf_to_returning_function() {
+        let bytecode = with_function_types(create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x01, STOP], vec![RETF]], None), &[
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 },
+            FunctionType { inputs: 0, outputs: 0, max_stack_height: 0 },
+        ]);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validate_eof_container(&container).is_ok());
+    }
+
+    #[test]
+    fn test_validate_callf_to_non_returning_function() {
+        let bytecode = with_function_types(create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x01, STOP], vec![STOP]], None), &[
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 },
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 },
+        ]);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::CallfToNonReturning(1)));
+    }
+
+    #[test]
+    fn test_validate_callf_to_missing_section() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x03, STOP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidSectionIndex(3)));
+    }
+
+    #[test]
+    fn test_subcontainer_references() {
+        let sub = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let code = vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x01, STOP];
+        let bytecode = create_eof_bytecode_with_containers(vec![code], vec![sub.clone(), sub], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.subcontainer_references(), vec![(0, 4, 0), (0, 10, 1)]);
+    }
+
+    // --- Warning Tests ---
+
+    #[test]
+    fn test_referenced_subcontainer_has_no_warning() {
+        let sub = create_initcode_bytecode();
+        let bytecode = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![sub], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert!(validation_warnings(&container).is_empty());
+        let config = ValidationConfig { strict_subcontainer_references: true, ..ValidationConfig::default() };
+        assert!(validate_eof_container_with_config(&container, &config).is_ok());
+    }
+
+    #[test]
+    fn test_orphan_subcontainer() {
+        let sub = create_initcode_bytecode();
+        let bytecode = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![sub.clone(), sub], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validation_warnings(&container), vec![EOFWarning::UnreferencedSubcontainer(1)]);
+        assert!(validate_eof_container(&container).is_ok()); // Only a warning by default
+        let config = ValidationConfig { strict_subcontainer_references: true, ..ValidationConfig::default() };
+        assert_eq!(validate_eof_container_with_config(&container, &config), Err(EOFError::UnreferencedSubcontainer(1)));
+    }
+
+    // --- Byte Order Tests ---
+
+    #[test]
+    fn test_read_u16_be() {
+        assert_eq!(read_u16_be(&[0x01, 0x02], 0), Ok(258)); // Big-endian, not 513
+        assert_eq!(read_u16_be(&[0x00, 0x01, 0x02], 1), Ok(258));
+        assert_eq!(read_u16_be(&[0x01], 0), Err(EOFError::UnexpectedEndOfInput));
+        assert_eq!(read_u16_be(&[0x01, 0x02], usize::MAX), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn test_section_size_is_big_endian() {
+        let mut bytecode = vec![0xEF, 0x00, EOF_VERSION];
+        bytecode.extend([SectionKind::Type as u8, 0x00, 0x04]);
+        bytecode.extend([SectionKind::Code as u8, 0x01, 0x02]); // 0x0102 = 258 bytes
+        bytecode.push(0x00);
+        bytecode.extend([0x00; 4]);
+        bytecode.extend([STOP; 258]);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.header.section_headers[1].size, 258);
+        assert_eq!(container.sections[1].len(), 258);
+    }
+
+    // --- Patching Tests ---
+
+    #[test]
+    fn test_replace_code_section() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP], vec![RETF]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        let patched = container.replace_code_section(0, vec![PUSH1, 0x01, POP, STOP]).unwrap();
+        assert_eq!(patched.header.section_headers[1].size, 4);
+        assert_eq!(patched.code_sections().collect::<Vec<_>>(), vec![&[PUSH1, 0x01, POP, STOP][..], &[RETF][..]]);
+        // The patched container re-encodes and re-validates
+        let reparsed = parse_eof_container(&patched.to_bytes()).unwrap();
+        assert_eq!(reparsed, patched);
+        assert!(validate_eof_container(&reparsed).is_ok());
+    }
+
+    #[test]
+    fn test_replace_code_section_out_of_range() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.replace_code_section(1, vec![STOP]), Err(EOFError::InvalidSectionIndex(1)));
+    }
+
+    #[test]
+    fn test_replace_code_section_too_large() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.replace_code_section(0, vec![STOP; 0x10000]), Err(EOFError::SectionTooLarge(0x10000)));
+    }
+
+    // --- Optimizer Analysis Tests ---
+
+    #[test]
+    fn test_fold_constant_branches() {
+        // PUSH0 RJUMPI +0 (0), PUSH1 0x01 RJUMPI +0 (4), STOP (9)
+        let code = vec![PUSH0, RJUMPI, 0x00, 0x00, PUSH1, 0x01, RJUMPI, 0x00, 0x00, STOP];
+        assert_eq!(fold_constant_branches(&code), vec![
+            ConstantBranch { offset: 0, direction: BranchDirection::FallThrough },
+            ConstantBranch { offset: 4, direction: BranchDirection::Taken },
+        ]);
+    }
+
+    #[test]
+    fn test_fold_constant_branches_ignores_dynamic_condition() {
+        // CALLDATASIZE RJUMPI +0, STOP
+        let code = vec![0x36, RJUMPI, 0x00, 0x00, STOP];
+        assert!(fold_constant_branches(&code).is_empty());
+    }
+
+    #[test]
+    fn test_fold_constant_branches_ignores_jump_target() {
+        // PUSH0 (0), RJUMPI +0 (1), RJUMP -6 (4) back onto the RJUMPI, STOP (7)
+        let code = vec![PUSH0, RJUMPI, 0x00, 0x00, RJUMP, 0xFF, 0xFA, STOP];
+        assert!(fold_constant_branches(&code).is_empty());
+    }
+
+    #[test]
+    fn test_validate_type_section_size_three() {
+        let mut bytecode = vec![0xEF, 0x00, EOF_VERSION];
+        bytecode.extend([SectionKind::Type as u8, 0x00, 0x03]);
+        bytecode.extend([SectionKind::Code as u8, 0x00, 0x01]);
+        bytecode.push(0x00);
+        bytecode.extend([0x00, 0x80, 0x00]); // Truncated Type entry
+        bytecode.push(STOP);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::TypeSectionSizeNotMultipleOf4(3)));
+        assert_eq!(container.function_types(), Err(EOFError::TypeSectionSizeNotMultipleOf4(3)));
+    }
+
+    // --- Instruction Indexing Tests ---
+
+    #[test]
+    fn test_instruction_count() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, ADD]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.instruction_count(0), Ok(3));
+        assert_eq!(container.instruction_count(1), Err(EOFError::InvalidSectionIndex(1)));
+    }
+
+    #[test]
+    fn test_offset_to_index() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, ADD]], None);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.offset_to_index(0, 0), Ok(0));
+        assert_eq!(container.offset_to_index(0, 2), Ok(1));
+        assert_eq!(container.offset_to_index(0, 4), Ok(2));
+        assert_eq!(container.offset_to_index(0, 3), Err(EOFError::JumpToImmediate(3)));
+        assert_eq!(container.offset_to_index(0, 5), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn test_data_padding() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0x00; 10]));
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.data_padding(), 0);
+        container.sections[2].truncate(4); // Declared 10, present 4
+        assert_eq!(container.data_padding(), 6);
+        let no_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], None)).unwrap();
+        assert_eq!(no_data.data_padding(), 0);
+    }
+
+    #[test]
+    fn test_simulate_rjumpv_in_range() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push(1).unwrap();
+        // RJUMPV max_index=1, table [+0, +2], then STOP, STOP, STOP
+        let code = vec![RJUMPV, 0x01, 0x00, 0x00, 0x00, 0x02, STOP, STOP, STOP];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 8); // 6 + 2
+    }
+
+    #[test]
+    fn test_simulate_rjumpv_huge_selector_falls_through() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        let mut selector = [0u8; 32];
+        selector[0] = 0x01; // Would wrap to 0 if only the low byte were read
+        stack.push_word(selector).unwrap();
+        let code = vec![RJUMPV, 0x01, 0x00, 0x00, 0x00, 0x02, STOP, STOP, STOP];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 6); // Past the jump table
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_simulate_rjumpv_selector_above_max_index() {
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        stack.push(2).unwrap();
+        let code = vec![RJUMPV, 0x01, 0x00, 0x00, 0x00, 0x02, STOP, STOP, STOP];
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 6);
+    }
+
+    // --- Structured Decoding Tests ---
+
+    #[test]
+    fn test_parse_decoded() {
+        let types = [
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 1 },
+            FunctionType { inputs: 1, outputs: 1, max_stack_height: 2 },
+        ];
+        let bytecode = with_function_types(
+            create_valid_eof_bytecode(vec![vec![PUSH0, CALLF, 0x00, 0x01, STOP], vec![PUSH0, ADD, RETF]], Some(vec![0xAA, 0xBB])),
+            &types,
+        );
+        let decoded = parse_decoded(&bytecode).unwrap();
+        assert_eq!(decoded.version, EOF_VERSION);
+        assert_eq!(decoded.functions, types.to_vec());
+        assert_eq!(decoded.code, vec![vec![PUSH0, CALLF, 0x00, 0x01, STOP], vec![PUSH0, ADD, RETF]]);
+        assert_eq!(decoded.data, Some(vec![0xAA, 0xBB]));
+        assert!(decoded.subcontainers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_decoded_subcontainers_and_errors() {
+        let sub = create_initcode_bytecode();
+        let bytecode = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![sub.clone()], None);
+        let decoded = parse_decoded(&bytecode).unwrap();
+        assert_eq!(decoded.subcontainers, vec![parse_decoded(&sub).unwrap()]);
+        assert_eq!(decoded.data, None);
+
+        let invalid = create_valid_eof_bytecode(vec![vec![INVALID]], None);
+        assert_eq!(parse_decoded(&invalid), Err(EOFError::InvalidOpcode(INVALID)));
+    }
+
+    #[test]
+    fn test_eq_ignoring_appended_data() {
+        let base = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0x01, 0x02]))).unwrap();
+        let mut appended = base.clone();
+        let data_index = appended.sections.len() - 1;
+        appended.sections[data_index].extend_from_slice(&[0x03, 0x04]);
+        assert_ne!(base, appended);
+        assert!(base.eq_ignoring_appended_data(&appended));
+        assert!(appended.eq_ignoring_appended_data(&base));
+
+        let mut changed = appended.clone();
+        changed.sections[data_index][0] = 0xFF;
+        assert!(!base.eq_ignoring_appended_data(&changed));
+
+        let other_code = parse_eof_container(&create_valid_eof_bytecode(vec![vec![INVALID]], Some(vec![0x01, 0x02]))).unwrap();
+        assert!(!base.eq_ignoring_appended_data(&other_code));
+    }
+
+    #[test]
+    fn test_find_trivial_infinite_loops() {
+        assert_eq!(find_trivial_infinite_loops(&[RJUMP, 0xFF, 0xFD]), vec![0]);
+        assert_eq!(find_trivial_infinite_loops(&[PUSH0, POP, RJUMP, 0xFF, 0xFD]), vec![2]);
+        // Jumping back to an earlier instruction is a loop, but not a trivial one.
+        assert!(find_trivial_infinite_loops(&[PUSH0, POP, RJUMP, 0xFF, 0xFB]).is_empty());
+        assert!(find_trivial_infinite_loops(&[RJUMP, 0x00, 0x00, STOP]).is_empty());
+    }
+
+    #[test]
+    fn test_simulated_stack_peek_and_depth() {
+        let mut stack = SimulatedStack::new();
+        assert_eq!(stack.depth(), 0);
+        assert_eq!(stack.peek(0), Err(EOFError::StackUnderflow));
+        stack.push(1).unwrap();
+        stack.push(2).unwrap();
+        assert_eq!(stack.depth(), 2);
+        assert_eq!(stack.peek(0).unwrap(), &word_from_u8(2)[..]);
+        assert_eq!(stack.peek(1).unwrap(), &word_from_u8(1)[..]);
+        assert_eq!(stack.peek(2), Err(EOFError::StackUnderflow));
+        // Peeking does not mutate the stack.
+        assert_eq!(stack.depth(), 2);
+    }
+
+    #[test]
+    fn test_container_sections_must_be_contiguous() {
+        let sub = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let code = vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP];
+        let valid = parse_eof_container(&create_eof_bytecode_with_containers(vec![code.clone()], vec![sub.clone(), sub.clone()], Some(vec![0xAA]))).unwrap();
+        assert_eq!(validate_structure(&valid), Ok(()));
+
+        // Reorder to Type, Container, Code, Container, Data
+        let mut interleaved = valid.clone();
+        interleaved.header.section_headers.swap(1, 2);
+        interleaved.sections.swap(1, 2);
+        assert_eq!(validate_structure(&interleaved), Err(EOFError::MalformedSectionHeader));
+
+        // Reorder to Type, Code, Container, Data, Container
+        let mut split = valid.clone();
+        split.header.section_headers.swap(3, 4);
+        split.sections.swap(3, 4);
+        assert_eq!(validate_structure(&split), Err(EOFError::MalformedSectionHeader));
+    }
+
+    #[test]
+    fn test_error_category() {
+        assert_eq!(EOFError::InvalidMagic.category(), ErrorCategory::Malformed);
+        assert_eq!(EOFError::NotEOF.category(), ErrorCategory::Malformed);
+        assert_eq!(EOFError::StackOverflow.category(), ErrorCategory::StackError);
+        assert_eq!(EOFError::InvalidOpcode(INVALID).category(), ErrorCategory::InvalidOpcode);
+        assert_eq!(EOFError::ContainerTooLarge.category(), ErrorCategory::SizeLimit);
+        assert_eq!(EOFError::InvalidVersion(0x02).category(), ErrorCategory::Unsupported);
+    }
+
+    #[test]
+    fn test_error_summary() {
+        assert_eq!(EOFError::TruncatedPushData.summary(), "error[E302]: Code section contains truncated PUSH data");
+        assert_eq!(EOFError::InvalidOpcode(INVALID).code(), 301);
+        assert!(EOFError::InvalidOpcode(INVALID).summary().starts_with("error[E301]: "));
+    }
+
+    #[test]
+    fn test_validate_truncated_immediates_at_section_end() {
+        let types = [
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 },
+            FunctionType { inputs: 0, outputs: 0, max_stack_height: 0 },
+        ];
+        let trailing_callf = with_function_types(create_valid_eof_bytecode(vec![vec![STOP, CALLF, 0x00], vec![RETF]], None), &types);
+        let container = parse_eof_container(&trailing_callf).unwrap();
+        assert_eq!(validate_eof_container(&container), Err(EOFError::TruncatedImmediate(CALLF)));
+
+        for code in [vec![STOP, RJUMP, 0x00], vec![STOP, RJUMPI], vec![STOP, RJUMPV, 0x01, 0x00, 0x00], vec![STOP, DUPN], vec![STOP, EXCHANGE]] {
+            let opcode = code[1];
+            let container = parse_eof_container(&create_valid_eof_bytecode(vec![code], None)).unwrap();
+            assert_eq!(validate_eof_container(&container), Err(EOFError::TruncatedImmediate(opcode)));
+        }
+
+        // Immediates are skipped rather than decoded as opcodes
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![RJUMP, 0x00, 0x00, DUPN, INVALID, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container(&container), Ok(()));
+    }
+
+    #[test]
+    fn test_fork_presets() {
+        let prague = ValidationConfig::prague();
+        let osaka = ValidationConfig::osaka();
+        assert!(prague.forbid_legacy_create && prague.forbid_removed_opcodes);
+        assert!(!prague.allow_eip663);
+        assert!(osaka.allow_eip663);
+        assert_ne!(prague, osaka);
+
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, DUPN, 0x00, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&container, &prague), Err(EOFError::InvalidOpcode(DUPN)));
+        assert_eq!(validate_eof_container_with_config(&container, &osaka), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_zero_size_data_section() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![]));
+        let container = parse_eof_container(&bytecode).unwrap();
+        let data_header = container.header.section_headers.last().unwrap();
+        assert_eq!((data_header.kind, data_header.size), (SectionKind::Data, 0));
+        assert_eq!(container.sections.last().unwrap(), &Vec::<u8>::new());
+        assert_eq!(container.to_bytes(), bytecode);
+        assert_eq!(validate_eof_container(&container), Ok(()));
+
+        // A stray byte after an empty final section is still rejected
+        let mut stray = bytecode.clone();
+        stray.push(0x00);
+        assert_eq!(parse_eof_container(&stray), Err(EOFError::SectionSizeMismatch));
+    }
+
+    #[test]
+    fn test_reachable_opcodes_excludes_dead_code() {
+        let types = [
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 1 },
+            FunctionType { inputs: 0, outputs: 1, max_stack_height: 1 },
+        ];
+        // Section 0 skips over a SELFDESTRUCT; validation would reject it, but the
+        // reachability walk does not validate.
+        let bytecode = with_function_types(
+            create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x01, RJUMP, 0x00, 0x01, SELFDESTRUCT, STOP], vec![PUSH0, RETF]], None),
+            &types,
+        );
+        let container = parse_eof_container(&bytecode).unwrap();
+        let reachable = reachable_opcodes(&container).unwrap();
+        assert_eq!(reachable, BTreeSet::from([STOP, PUSH0, CALLF, RETF, RJUMP]));
+        assert!(!reachable.contains(&SELFDESTRUCT));
+    }
+
+    #[test]
+    fn test_validate_eof_reader() {
+        use std::io::Cursor;
+
+        // The streaming and in-memory validators agree on acceptance and rejection
+        let agree = |bytecode: &[u8]| {
+            let in_memory = parse_eof_container(bytecode).and_then(|container| validate_eof_container(&container));
+            assert_eq!(validate_eof_reader(Cursor::new(bytecode)), in_memory);
+            in_memory
+        };
+
+        let valid = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![create_initcode_bytecode()], Some(vec![0xAA]));
+        assert_eq!(agree(&valid), Ok(()));
+
+        let runtime = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let to_runtime = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![runtime], None);
+        assert_eq!(agree(&to_runtime), Err(EOFError::EofCreateTargetNotInitcode(0)));
+
+        let dangling = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x01, STOP]], vec![create_initcode_bytecode()], None);
+        assert_eq!(agree(&dangling), Err(EOFError::InvalidSectionIndex(1)));
+
+        // Section bodies fill the parse budget, so only the header pushes it over the initcode limit
+        let data_size = MAX_CONTAINER_SIZE - 4 - 7 - create_initcode_bytecode().len();
+        let too_large = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![create_initcode_bytecode()], Some(vec![0xAA; data_size]));
+        assert_eq!(agree(&too_large), Err(EOFError::InitcodeTooLarge(too_large.len())));
+
+        // An invalid opcode in the first code section stops before the rest is read
+        let invalid = create_valid_eof_bytecode(vec![vec![INVALID], vec![STOP]], Some(vec![0xBB; 64]));
+        let mut cursor = Cursor::new(&invalid);
+        assert_eq!(validate_eof_reader(&mut cursor), Err(EOFError::InvalidOpcode(INVALID)));
+        assert!((cursor.position() as usize) < invalid.len());
+
+        // The streaming result matches the in-memory one for truncated and padded input
+        let mut stray = valid.clone();
+        stray.push(0x00);
+        assert_eq!(validate_eof_reader(Cursor::new(&stray)), Err(EOFError::SectionSizeMismatch));
+        assert_eq!(validate_eof_reader(Cursor::new(&valid[..valid.len() - 1])), Err(EOFError::UnexpectedEndOfInput));
+        assert_eq!(validate_eof_reader(Cursor::new(&valid[..3])), parse_eof_container(&valid[..3]).map(|_| ()));
+
+        // A short or foreign prefix is reported like `parse_header` does, not as truncation
+        assert_eq!(validate_eof_reader(Cursor::new(&[0x00, 0x00])), Err(EOFError::NotEOF));
+        assert_eq!(validate_eof_reader(Cursor::new(&[0xEF, 0x01, 0x01, 0x01])), Err(EOFError::InvalidMagic));
+        assert_eq!(validate_eof_reader(Cursor::new(&[0xEF])), Err(EOFError::UnexpectedEndOfInput));
+    }
+
+    #[test]
+    fn test_gas_opcode_retained() {
+        let info = opcode_info(GAS).unwrap();
+        assert_eq!((info.name, info.inputs, info.outputs), ("GAS", 0, 1));
+
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![GAS, POP, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container(&container), Ok(()));
+
+        let mut stack = SimulatedStack::new();
+        let mut pc = 0;
+        simulate_eof_step(&[GAS, STOP], &mut pc, &mut stack).unwrap();
+        assert_eq!((pc, stack.depth()), (1, 1));
+    }
+
+    #[test]
+    fn test_simulate_eof_trace() {
+        let code = [PUSH1, 0x01, PUSH1, 0x02, ADD, STOP];
+        let mut stack = SimulatedStack::new();
+        let trace = simulate_eof_trace(&code, &mut stack, 10).unwrap();
+        let expected = [(0, PUSH1, 0), (2, PUSH1, 1), (4, ADD, 2), (5, STOP, 1)]
+            .map(|(pc, opcode, stack_height)| TraceEntry { pc, opcode, stack_height });
+        assert_eq!(trace, expected);
+        assert_eq!(simulate_eof_trace(&[RJUMP, 0xFF, 0xFD], &mut SimulatedStack::new(), 5), Err(EOFError::StepLimitExceeded));
+    }
+
+    #[test]
+    fn test_allow_memory_ops() {
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, PUSH0, MSTORE, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container(&container), Ok(()));
+        let config = ValidationConfig { allow_memory_ops: false, ..ValidationConfig::default() };
+        assert_eq!(validate_eof_container_with_config(&container, &config), Err(EOFError::OpcodeDisabledByConfig(MSTORE)));
+
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&container, &config), Ok(()));
+    }
+
+    #[test]
+    fn test_code_identical() {
+        let a = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0x01]))).unwrap();
+        let b = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0x02, 0x03]))).unwrap();
+        let no_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], None)).unwrap();
+        assert!(code_identical(&a, &b));
+        assert!(code_identical(&a, &no_data));
+
+        let other_code = parse_eof_container(&create_valid_eof_bytecode(vec![vec![INVALID]], Some(vec![0x01]))).unwrap();
+        assert!(!code_identical(&a, &other_code));
+    }
+
+    #[test]
+    fn test_validate_stack_heights_callf_effect() {
+        let types = [
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 3 },
+            FunctionType { inputs: 2, outputs: 1, max_stack_height: 2 },
+        ];
+        // The CALLF consumes two items and leaves one, so the later pushes peak at 3, not 4
+        let caller = [PUSH0, PUSH0, CALLF, 0x00, 0x01, PUSH0, PUSH0, STOP];
+        assert_eq!(validate_stack_heights(&caller, &types, 0), Ok(3));
+        assert_eq!(validate_stack_heights(&[ADD, RETF], &types, 1), Ok(2));
+
+        let too_few_inputs = [PUSH0, CALLF, 0x00, 0x01, STOP];
+        assert_eq!(validate_stack_heights(&too_few_inputs, &types, 0), Err(EOFError::StackUnderflow));
+        assert_eq!(validate_stack_heights(&[CALLF, 0x00, 0x02, STOP], &types, 0), Err(EOFError::InvalidSectionIndex(2)));
+        // Without Type information the code-only analysis still treats CALLF as neutral
+        assert_eq!(min_initial_stack(&too_few_inputs), Ok(0));
+    }
+
+    #[test]
+    fn test_initcode_too_large() {
+        let sub = create_initcode_bytecode();
+        let code = vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP];
+        // Section bodies fill the parse budget exactly, so the header pushes the total over
+        let data_size = MAX_CONTAINER_SIZE - 4 - code.len() - sub.len();
+        let oversized = parse_eof_container(&create_eof_bytecode_with_containers(vec![code.clone()], vec![sub.clone()], Some(vec![0x00; data_size]))).unwrap();
+        assert!(oversized.is_initcode());
+        let size = oversized.to_bytes().len();
+        assert!(size > MAX_INITCODE_SIZE);
+        assert_eq!(validate_eof_container(&oversized), Err(EOFError::InitcodeTooLarge(size)));
+
+        let header_len = oversized.header.encoded_len();
+        let at_limit = parse_eof_container(&create_eof_bytecode_with_containers(vec![code], vec![sub], Some(vec![0x00; data_size - header_len]))).unwrap();
+        assert_eq!(at_limit.to_bytes().len(), MAX_INITCODE_SIZE);
+        assert_eq!(validate_eof_container(&at_limit), Ok(()));
+
+    }
+
+    #[test]
+    fn test_runtime_code_too_large() {
+        // A STOP-only runtime container is 18 bytes plus its data
+        let deploy = |data_size: usize| {
+            let runtime = create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0x00; data_size]));
+            parse_eof_container(&create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime], None)).unwrap()
+        };
+        let at_limit = deploy(MAX_CODE_SIZE - 18);
+        assert_eq!(at_limit.subcontainer(0).unwrap().to_bytes().len(), MAX_CODE_SIZE);
+        assert_eq!(validate_eof_container(&at_limit), Ok(()));
+        assert_eq!(validate_eof_container(&deploy(MAX_CODE_SIZE - 17)), Err(EOFError::RuntimeCodeTooLarge(MAX_CODE_SIZE + 1)));
+    }
+
+    #[test]
+    fn test_max_stack_heights() {
+        let types = [
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 2 },
+            FunctionType { inputs: 0, outputs: 0, max_stack_height: 5 },
+        ];
+        let bytecode = with_function_types(create_valid_eof_bytecode(vec![vec![STOP], vec![RETF]], None), &types);
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.max_stack_heights(), Ok(vec![2, 5]));
+    }
+
+    #[test]
+    fn test_relative_jump_cannot_cross_sections() {
+        let types = [
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 },
+            FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 },
+        ];
+        // In the flat buffer, offset 4 past this RJUMP lands on section 1's STOP,
+        // but targets are bounded by section 0's own length.
+        let bytecode = with_function_types(create_valid_eof_bytecode(vec![vec![RJUMP, 0x00, 0x01, STOP], vec![STOP]], None), &types);
+        let container = parse_eof_container(&bytecode).unwrap();
+        let err = validate_eof_container(&container).unwrap_err();
+        assert_eq!(err, EOFError::InvalidJumpDestination(0));
+        assert!(err.to_string().contains("cannot cross sections"));
+
+        let in_range = with_function_types(create_valid_eof_bytecode(vec![vec![RJUMP, 0x00, 0x00, STOP], vec![STOP]], None), &types);
+        assert_eq!(validate_eof_container(&parse_eof_container(&in_range).unwrap()), Ok(()));
+
+        let into_immediate = parse_eof_container(&create_valid_eof_bytecode(vec![vec![RJUMP, 0xFF, 0xFE, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container(&into_immediate), Err(EOFError::JumpToImmediate(1)));
+    }
+
+    #[test]
+    fn test_error_display_unchanged() {
+        assert_eq!(EOFError::InvalidVersion(2).to_string(), "Invalid EOF version: 2");
+        assert_eq!(EOFError::InvalidOpcode(0xFE).to_string(), "Code section contains invalid opcode: 0xfe");
+        assert_eq!(
+            EOFError::DuplicateSection { kind: SectionKind::Data, at_index: 3 }.to_string(),
+            "Duplicate section kind: Data at header index 3"
+        );
+        assert_eq!(EOFError::ContainerTooLarge.to_string(), format!("Total declared section size exceeds {} bytes", MAX_CONTAINER_SIZE));
+    }
+
+    #[test]
+    fn test_parse_eof_file() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, STOP]], Some(vec![0xAB]));
+        let hex: String = bytecode.iter().map(|b| format!("{:02x}", b)).collect();
+        let path = std::env::temp_dir().join(format!("eof-prototype-{}.hex", std::process::id()));
+
+        std::fs::write(&path, format!("0x{}\n", hex)).unwrap();
+        assert_eq!(parse_eof_file(&path), parse_eof_container(&bytecode));
+        std::fs::write(&path, "ef00zz").unwrap();
+        assert_eq!(parse_eof_file(&path), Err(EOFError::InvalidHex));
+        std::fs::write(&path, "ef0").unwrap();
+        assert_eq!(parse_eof_file(&path), Err(EOFError::InvalidHex));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(parse_eof_file(&path), Err(EOFError::Io(_))));
+    }
+
+    #[test]
+    fn test_duplicate_code_sections() {
+        let duplicated = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP], vec![PUSH0, STOP], vec![PUSH0, STOP]], None)).unwrap();
+        assert_eq!(duplicated.duplicate_code_sections(), vec![(1, 2)]);
+
+        let distinct = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP], vec![PUSH0, STOP]], None)).unwrap();
+        assert!(distinct.duplicate_code_sections().is_empty());
+    }
+
+    #[test]
+    fn test_deployed_size() {
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0xAA; 4]))).unwrap();
+        let size = container.to_bytes().len();
+        assert_eq!(deployed_size(&container, 0), Ok(size));
+        assert_eq!(deployed_size(&container, 100), Ok(size + 100));
+        assert_eq!(deployed_size(&container, u16::MAX as usize), Err(EOFError::SectionTooLarge(u16::MAX as usize + 4)));
+        assert_eq!(deployed_size(&container, MAX_CONTAINER_SIZE), Err(EOFError::ContainerTooLarge));
+
+        // A container without a Data section gains a Data header
+        let no_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], None)).unwrap();
+        assert_eq!(deployed_size(&no_data, 100), Ok(no_data.to_bytes().len() + 3 + 100));
+    }
+
+    #[test]
+    fn test_validate_stack_heights_join_mismatch() {
+        let types = [FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 2 }];
+        // PUSH0 RJUMPI +1 PUSH0 STOP: the fall-through path reaches STOP with one more item
+        let unbalanced = [PUSH0, RJUMPI, 0x00, 0x01, PUSH0, STOP];
+        assert_eq!(validate_stack_heights(&unbalanced, &types, 0), Err(EOFError::StackHeightMismatch(5)));
+
+        // PUSH0 RJUMPI +4 PUSH0 RJUMP +1 PUSH0 STOP: both branches push once before the join
+        let balanced = [PUSH0, RJUMPI, 0x00, 0x04, PUSH0, RJUMP, 0x00, 0x01, PUSH0, STOP];
+        assert_eq!(validate_stack_heights(&balanced, &types, 0), Ok(1));
+    }
+
+    #[test]
+    fn test_jump_targets() {
+        // A forward RJUMPI to offset 5 and a backward RJUMP (8 - 8) to offset 0
+        let code = [PUSH0, RJUMPI, 0x00, 0x01, STOP, RJUMP, 0xFF, 0xF8, STOP];
+        assert_eq!(jump_targets(&code), Ok(BTreeSet::from([0, 5])));
+        assert_eq!(jump_targets(&[STOP]), Ok(BTreeSet::new()));
+        assert_eq!(jump_targets(&[RJUMP, 0x00, 0x05]), Err(EOFError::InvalidJumpDestination(0)));
+    }
+
+    #[test]
+    fn test_deny_warnings() {
+        let config = ValidationConfig { deny_warnings: true, ..ValidationConfig::default() };
+        let unused_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0xAA]))).unwrap();
+        assert_eq!(validation_warnings(&unused_data), vec![EOFWarning::UnusedDataSection]);
+        assert_eq!(validate_eof_container(&unused_data), Ok(()));
+        assert_eq!(validate_eof_container_with_config(&unused_data, &config), Err(EOFError::UnusedDataSection));
+
+        let used_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![DATASIZE, POP, STOP]], Some(vec![0xAA]))).unwrap();
+        assert!(validation_warnings(&used_data).is_empty());
+        assert_eq!(validate_eof_container_with_config(&used_data, &config), Ok(()));
+
+        let sub = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let unreferenced = parse_eof_container(&create_eof_bytecode_with_containers(vec![vec![STOP]], vec![sub], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&unreferenced, &config), Err(EOFError::UnreferencedSubcontainer(0)));
+    }
+
+    #[test]
+    fn test_section_accessor_and_length_mismatch() {
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0xAA]))).unwrap();
+        let (header, body) = container.section(1).unwrap();
+        assert_eq!((header.kind, body), (SectionKind::Code, &[STOP][..]));
+        assert_eq!(container.section(3), None);
 
-            if condition != 0 { // If condition is true (non-zero)
-                *pc = (*pc as isize + 3 + offset as isize) as usize;
-            } else {
-                *pc += 3; // Skip opcode and immediate
-            }
-        },
-        // --- Default: unknown opcode, just advance PC ---
-        _ => *pc += 1,
+        let mut missing_body = container.clone();
+        missing_body.sections.pop();
+        assert_eq!(missing_body.section(2), None);
+        assert_eq!(validate_eof_container(&missing_body), Err(EOFError::MalformedSectionHeader));
+
+        let mut extra_body = container.clone();
+        extra_body.sections.push(vec![0x00]);
+        assert_eq!(validate_eof_container(&extra_body), Err(EOFError::MalformedSectionHeader));
+
+        // The editing helpers report the mismatch instead of indexing past the bodies
+        let mut no_code = container.clone();
+        no_code.sections.truncate(1);
+        assert_eq!(no_code.replace_code_section(0, vec![STOP]), Err(EOFError::MalformedSectionHeader));
+        assert_eq!(no_code.rewrite_offsets_after_insert(0, 0, 0), Err(EOFError::MalformedSectionHeader));
+        assert_eq!(no_code.push_code(vec![STOP], FunctionType::from_bytes([0, NON_RETURNING_FUNCTION, 0, 0])), Err(EOFError::MalformedSectionHeader));
+        assert_eq!(extra_body.push_code(vec![STOP], FunctionType::from_bytes([0, NON_RETURNING_FUNCTION, 0, 0])), Err(EOFError::MalformedSectionHeader));
     }
-    Ok(())
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_check_returned_containers() {
+        // The subcontainer has the wrong magic, so it could never be deployed as EOF
+        let mut malformed = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        malformed[1] = 0x01;
+        let initcode = parse_eof_container(&create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![malformed], None)).unwrap();
+        assert_eq!(validate_eof_container(&initcode), Err(EOFError::InvalidMagic));
+        let config = ValidationConfig { check_returned_containers: true, ..ValidationConfig::default() };
+        assert_eq!(validate_eof_container_with_config(&initcode, &config), Err(EOFError::InvalidReturnedContainer(0)));
+    }
 
-    // --- Helper function to create a minimal valid EOF container ---
-    fn create_valid_eof_bytecode(code_sections: Vec<Vec<u8>>, data_section: Option<Vec<u8>>) -> Vec<u8> {
-        let mut bytecode = Vec::new();
-        bytecode.extend_from_slice(&EOF_MAGIC.to_be_bytes()); // Magic
-        bytecode.push(EOF_VERSION); // Version
+    #[test]
+    fn test_single_function() {
+        let container = EOFContainer::single_function(vec![PUSH1, 0x01, STOP]).unwrap();
+        assert_eq!(container.function_types(), Ok(vec![FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 1 }]));
+        assert_eq!(validate_eof_container(&container), Ok(()));
+        assert_eq!(parse_eof_container(&container.to_bytes()), Ok(container));
 
-        // Dummy Type section (size depends on number of code sections)
-        let type_size = (code_sections.len() * 4) as u16; // Assuming 4 bytes per function entry
-        bytecode.push(SectionKind::Type as u8);
-        bytecode.extend_from_slice(&type_size.to_be_bytes());
+        assert_eq!(EOFContainer::single_function(vec![POP, STOP]), Err(EOFError::StackUnderflow));
+    }
 
-        // Code sections
-        for code in &code_sections {
-            bytecode.push(SectionKind::Code as u8);
-            bytecode.extend_from_slice(&(code.len() as u16).to_be_bytes());
-        }
+    #[test]
+    fn test_opcodes_module_reexports() {
+        assert_eq!(opcodes::control_flow::RJUMP, RJUMP);
+        assert_eq!(opcodes::push::PUSH1, PUSH1);
+        assert_eq!(opcodes::eof_specific::EOFCREATE, EOFCREATE);
+        assert_eq!(opcodes::arithmetic::ADD, 0x01);
+    }
 
-        // Data section
-        if let Some(data) = &data_section {
-            bytecode.push(SectionKind::Data as u8);
-            bytecode.extend_from_slice(&(data.len() as u16).to_be_bytes());
-        }
+    #[test]
+    fn test_declared_sizes_must_match_bodies() {
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP]], None)).unwrap();
+        let mut overclaimed = container.clone();
+        overclaimed.header.section_headers[1].size += 2;
+        assert_eq!(validate_eof_container(&overclaimed), Err(EOFError::SectionSizeMismatch));
 
-        bytecode.push(0x00); // Terminator
+        // Each section is checked on its own, so mismatches cannot offset each other
+        let mut shifted = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP], vec![STOP]], None)).unwrap();
+        shifted.header.section_headers[1].size = 2;
+        shifted.header.section_headers[2].size = 2;
+        assert_eq!(validate_structure(&shifted), Err(EOFError::SectionSizeMismatch));
 
-        // Section contents
-        bytecode.extend(vec![0x00; type_size as usize]); // Dummy type content (e.g., input/output counts)
-        for code in code_sections {
-            bytecode.extend(code);
-        }
-        if let Some(data) = data_section {
-            bytecode.extend(data);
-        }
-        bytecode
+        // A deferred (short) Data body is still accepted
+        let mut deferred = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0xAA; 4]))).unwrap();
+        deferred.sections[2].truncate(1);
+        assert_eq!(deferred.data_padding(), 3);
+        assert_eq!(validate_structure(&deferred), Ok(()));
     }
 
-    // --- Parse tests (from previous version, ensuring they still pass) ---
     #[test]
-    fn test_parse_simple_eof_container() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![0x01, 0x02]], Some(vec![0x03, 0x04]));
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(container.header.version, 0x01);
-        assert_eq!(container.sections.len(), 3);
-        // Section 0 is Type, Section 1 is Code, Section 2 is Data
-        assert_eq!(container.sections[1], vec![0x01, 0x02]); // Code section
-        assert_eq!(container.sections[2], vec![0x03, 0x04]); // Data section
+    fn test_entry_pc_is_boundary() {
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None)).unwrap();
+        assert_eq!(container.entry_pc(), 0);
+        assert_eq!(container.offset_to_index(0, container.entry_pc()), Ok(0));
+        assert_eq!(validate_eof_container(&container), Ok(()));
     }
 
     #[test]
-    fn test_invalid_magic() {
-        let bytecode = vec![0xDE, 0xAD, 0x01, 0x00];
-        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::InvalidMagic));
+    fn test_opcode_context() {
+        assert_eq!(opcode_context(RETURNCONTRACT), OpcodeContext::InitcodeOnly);
+        assert_eq!(opcode_context(RETURN), OpcodeContext::RuntimeOnly);
+        assert_eq!(opcode_context(ADD), OpcodeContext::Both);
+
+        // RETURNCONTRACT in initcode deploying a plain runtime container is valid
+        let runtime = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, STOP]], None);
+        let initcode = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime], None);
+        assert_eq!(validate_eof_container(&parse_eof_container(&initcode).unwrap()), Ok(()));
+
+        // ...but the deployed runtime container may not use it in turn
+        let nested = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![initcode], None);
+        assert_eq!(validate_eof_container(&parse_eof_container(&nested).unwrap()), Err(EOFError::ReturnContractInRuntime));
+
+        // An EOFCREATE target runs as initcode, so it may not STOP
+        let runtime = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let stopping = create_eof_bytecode_with_containers(vec![vec![PUSH0, RJUMPI, 0x00, 0x01, STOP, PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime], None);
+        let factory = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![stopping], None);
+        assert_eq!(validate_eof_container(&parse_eof_container(&factory).unwrap()), Err(EOFError::OpcodeWrongContext(STOP)));
     }
 
     #[test]
-    fn test_invalid_version() {
-        let bytecode = vec![0xEF, 0x00, 0x02, 0x00];
-        assert_eq!(parse_eof_container(&bytecode), Err(EOFError::InvalidVersion(0x02)));
+    fn test_validate_function_count() {
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP], vec![STOP]], None)).unwrap();
+        assert_eq!(validate_function_count(&container, 2), Ok(()));
+        assert_eq!(validate_function_count(&container, 3), Err(EOFError::FunctionCountMismatch { expected: 3, found: 2 }));
     }
-    // --- End Parse tests ---
 
-    // --- EIP-3670 Validation Tests ---
+    #[test]
+    fn test_push_code() {
+        let mut container = EOFContainer {
+            header: EOFHeader { version: EOF_VERSION, section_headers: vec![SectionHeader { kind: SectionKind::Type, size: 0 }] },
+            sections: vec![Vec::new()],
+        };
+        container.push_code(vec![CALLF, 0x00, 0x01, STOP], FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 }).unwrap();
+        container.push_code(vec![RETF], FunctionType { inputs: 0, outputs: 0, max_stack_height: 0 }).unwrap();
+        assert_eq!(container.header.section_headers[0].size, 8);
+        assert_eq!(container.sections[0].len(), 8);
+        assert_eq!(container.code_sections().collect::<Vec<_>>(), vec![&[CALLF, 0x00, 0x01, STOP][..], &[RETF][..]]);
+        assert_eq!(validate_eof_container(&container), Ok(()));
+
+        // Code goes ahead of an existing Data section
+        let mut with_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], Some(vec![0xAA]))).unwrap();
+        with_data.push_code(vec![STOP], FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 }).unwrap();
+        assert_eq!(with_data.header.section_headers.last().unwrap().kind, SectionKind::Data);
+        assert_eq!(validate_structure(&with_data), Ok(()));
+    }
 
     #[test]
-    fn test_validate_valid_eof() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, 0x01]], None); // PUSH1 01, PUSH1 02, ADD
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert!(validate_eof_container(&container).is_ok());
+    fn test_simulate_strict_unknown() {
+        let code = [0x0C, STOP];
+        let mut pc = 0;
+        let mut stack = SimulatedStack::new();
+        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
+        assert_eq!(pc, 1);
+
+        let mut pc = 0;
+        let strict = SimulateConfig { strict_unknown: true, ..SimulateConfig::default() };
+        assert_eq!(simulate_eof_step_cfg(&code, &mut pc, &mut stack, &strict), Err(EOFError::InvalidOpcode(0x0C)));
+        assert_eq!(pc, 0);
+        // Known opcodes still step normally in strict mode
+        simulate_eof_step_cfg(&[PUSH1, 0x01, STOP], &mut pc, &mut stack, &strict).unwrap();
+        assert_eq!(pc, 2);
     }
 
     #[test]
-    fn test_validate_invalid_opcode() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, INVALID]], None);
+    fn test_jumpf_chain_reachability_and_stack() {
+        let non_returning = FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 };
+        let types = [non_returning, non_returning];
+        let bytecode = with_function_types(create_valid_eof_bytecode(vec![vec![JUMPF, 0x00, 0x01], vec![STOP]], None), &types);
         let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(INVALID)));
+        assert_eq!(reachable_opcodes(&container), Ok(BTreeSet::from([STOP, JUMPF])));
+        assert_eq!(validate_eof_container(&container), Ok(()));
+        for (section, code) in container.code_sections().enumerate() {
+            assert_eq!(validate_stack_heights(code, &types, section), Ok(0));
+        }
+
+        // A non-returning function cannot tail-call into a returning one
+        let returning = [non_returning, FunctionType { inputs: 0, outputs: 0, max_stack_height: 0 }];
+        assert_eq!(validate_stack_heights(&[JUMPF, 0x00, 0x01], &returning, 0), Err(EOFError::JumpfOutputMismatch(1)));
+        let needs_input = [non_returning, FunctionType { inputs: 1, outputs: NON_RETURNING_FUNCTION, max_stack_height: 1 }];
+        assert_eq!(validate_stack_heights(&[JUMPF, 0x00, 0x01], &needs_input, 0), Err(EOFError::StackUnderflow));
+        assert_eq!(validate_stack_heights(&[PUSH0, JUMPF, 0x00, 0x01], &needs_input, 0), Ok(1));
     }
 
     #[test]
-    fn test_validate_selfdestruct_forbidden() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, SELFDESTRUCT]], None);
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(SELFDESTRUCT)));
+    fn test_subcontainer_by_index() {
+        let first = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let second = create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP]], Some(vec![0xAA]));
+        let code = vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x01, STOP];
+        let container = parse_eof_container(&create_eof_bytecode_with_containers(vec![code], vec![first.clone(), second.clone()], None)).unwrap();
+        assert_eq!(container.subcontainer(0), parse_eof_container(&first));
+        assert_eq!(container.subcontainer(1), parse_eof_container(&second));
+        assert_eq!(container.subcontainer(2), Err(EOFError::InvalidSectionIndex(2)));
     }
 
     #[test]
-    fn test_validate_jump_forbidden() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, JUMP]], None);
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(JUMP)));
+    fn test_static_datacopy_out_of_bounds() {
+        // size 4, offset 2, mem_offset 0 against 4 bytes of data
+        let overrun = vec![PUSH1, 0x04, PUSH1, 0x02, PUSH0, DATACOPY, STOP];
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![overrun], Some(vec![0xAA; 4]))).unwrap();
+        assert_eq!(validation_warnings(&container), vec![EOFWarning::DataCopyOutOfBounds { section: 0, offset: 5 }]);
+        assert_eq!(validate_eof_container(&container), Ok(()));
+
+        let in_bounds = vec![PUSH1, 0x02, PUSH1, 0x02, PUSH0, DATACOPY, STOP];
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![in_bounds], Some(vec![0xAA; 4]))).unwrap();
+        assert!(validation_warnings(&container).is_empty());
+
+        // The offset comes from DATASIZE, so the copy is not checked
+        let dynamic = vec![PUSH1, 0x04, DATASIZE, PUSH0, DATACOPY, STOP];
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![dynamic], Some(vec![0xAA; 4]))).unwrap();
+        assert!(validation_warnings(&container).is_empty());
     }
 
+    #[cfg(feature = "keccak")]
     #[test]
-    fn test_validate_jumpi_forbidden() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, JUMPI]], None);
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(JUMPI)));
+    fn test_code_identity_hash() {
+        let code = vec![PUSH0, POP, STOP];
+        let a = parse_eof_container(&create_valid_eof_bytecode(vec![code.clone()], Some(vec![0x01, 0x02]))).unwrap();
+        let b = parse_eof_container(&create_valid_eof_bytecode(vec![code], Some(vec![0xFF; 8]))).unwrap();
+        assert_eq!(a.code_identity_hash(), b.code_identity_hash());
+
+        let other = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH0, PUSH0, STOP]], Some(vec![0x01, 0x02]))).unwrap();
+        assert_ne!(a.code_identity_hash(), other.code_identity_hash());
     }
 
     #[test]
-    fn test_validate_pc_forbidden() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PC]], None);
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::JumpDestForbidden(PC)));
+    fn test_basic_blocks_marks_dead_code() {
+        // RJUMPI over a PUSH0, STOP, then dead PUSH0 POP STOP
+        let code = vec![PUSH0, RJUMPI, 0x00, 0x01, PUSH0, STOP, PUSH0, POP, STOP];
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![code], None)).unwrap();
+        let blocks = container.basic_blocks(0).unwrap();
+        assert_eq!(blocks, vec![
+            BasicBlock { start: 0, end: 4, reachable: true, successors: vec![2, 1] },
+            BasicBlock { start: 4, end: 5, reachable: true, successors: vec![2] },
+            BasicBlock { start: 5, end: 6, reachable: true, successors: vec![] },
+            BasicBlock { start: 6, end: 9, reachable: false, successors: vec![] },
+        ]);
+        assert_eq!(container.basic_blocks(1), Err(EOFError::InvalidSectionIndex(1)));
     }
 
     #[test]
-    fn test_validate_truncated_push_data() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH2]], None); // PUSH2 needs 2 bytes, only 1 provided
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::TruncatedPushData));
+    fn test_allowed_opcodes() {
+        let config = ValidationConfig { allowed_opcodes: Some(HashSet::from([PUSH1, ADD, STOP])), ..ValidationConfig::default() };
+        let add = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, ADD, POP, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&add, &config), Err(EOFError::OpcodeNotAllowed(POP)));
+
+        let add = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, ADD, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&add, &config), Ok(()));
+        let mul = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, MUL, STOP]], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&mul, &config), Err(EOFError::OpcodeNotAllowed(MUL)));
+        assert_eq!(validate_eof_container(&mul), Ok(()));
+
+        // Terminating opcodes are always permitted
+        let ret = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x00, PUSH1, 0x00, RETURN]], None)).unwrap();
+        assert_eq!(validate_eof_container_with_config(&ret, &config), Ok(()));
     }
 
     #[test]
-    fn test_validate_empty_code_section() {
-        let bytecode = create_valid_eof_bytecode(vec![vec![]], None);
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::SectionSizeMismatch));
+    fn test_stack_height_at() {
+        let ft = FunctionType { inputs: 1, outputs: NON_RETURNING_FUNCTION, max_stack_height: 3 };
+        let code = vec![PUSH1, 0x01, PUSH0, POP, STOP, PUSH0];
+        assert_eq!(stack_height_at(&code, &ft, 0), Ok(1));
+        assert_eq!(stack_height_at(&code, &ft, 3), Ok(3)); // After the two PUSHes
+        assert_eq!(stack_height_at(&code, &ft, 4), Ok(2));
+        assert_eq!(stack_height_at(&code, &ft, 1), Err(EOFError::JumpToImmediate(1)));
+        assert_eq!(stack_height_at(&code, &ft, 5), Err(EOFError::UnreachableOffset(5)));
+        assert_eq!(stack_height_at(&code, &ft, 6), Err(EOFError::UnexpectedEndOfInput));
     }
 
     #[test]
-    fn test_validate_type_section_size_mismatch() {
-        let mut bytecode = Vec::new();
-        bytecode.extend_from_slice(&EOF_MAGIC.to_be_bytes()); // Magic
-        bytecode.push(EOF_VERSION); // Version
-        bytecode.push(SectionKind::Type as u8);
-        bytecode.extend_from_slice(&(2 as u16).to_be_bytes()); // Type size 2, but 1 code section expects 4
-        bytecode.push(SectionKind::Code as u8);
-        bytecode.extend_from_slice(&(1 as u16).to_be_bytes());
-        bytecode.push(0x00); // Terminator
-        bytecode.extend(vec![0x00, 0x00]); // Dummy type content (size 2)
-        bytecode.extend(vec![0x01]); // Code content
+    fn test_find_dead_pushes() {
+        assert_eq!(find_dead_pushes(&[PUSH1, 0x01, POP, STOP]), vec![0]);
+        assert_eq!(find_dead_pushes(&[PUSH0, PUSH1, 0x01, ADD, STOP]), Vec::<usize>::new());
+        // A PUSH2 whose immediate byte equals POP is not a PUSH/POP pair
+        assert_eq!(find_dead_pushes(&[PUSH2, 0x00, POP, STOP]), Vec::<usize>::new());
+        // POP reached by a jump may pop a value pushed elsewhere
+        assert_eq!(find_dead_pushes(&[PUSH0, RJUMPI, 0x00, 0x01, PUSH0, POP, STOP]), Vec::<usize>::new());
+    }
 
-        let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::MalformedSectionHeader));
+    #[test]
+    fn test_validate_and_decode() {
+        let mut container = EOFContainer::single_function(vec![STOP]).unwrap();
+        let callee = FunctionType { inputs: 1, outputs: 2, max_stack_height: 2 };
+        container.push_code(vec![PUSH0, RETF], callee).unwrap();
+        let entry = FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 };
+        assert_eq!(validate_and_decode(&container), Ok(vec![entry, callee]));
+
+        let invalid = container.replace_code_section(1, vec![INVALID]).unwrap();
+        assert_eq!(validate_and_decode(&invalid), Err(EOFError::InvalidOpcode(INVALID)));
     }
 
     #[test]
-    fn test_validate_code_section_count_mismatch() {
-        let mut bytecode = Vec::new();
-        bytecode.extend_from_slice(&EOF_MAGIC.to_be_bytes()); // Magic
-        bytecode.push(EOF_VERSION); // Version
-        bytecode.push(SectionKind::Type as u8);
-        bytecode.extend_from_slice(&(8 as u16).to_be_bytes()); // Type size 8, but only 1 code section
-        bytecode.push(SectionKind::Code as u8);
-        bytecode.extend_from_slice(&(1 as u16).to_be_bytes());
-        bytecode.push(0x00); // Terminator
-        bytecode.extend(vec![0x00; 8]); // Dummy type content (size 8)
-        bytecode.extend(vec![0x01]); // Code content
+    fn test_simulate_function_run_enforces_declared_max_stack() {
+        let ft = FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 2 };
+        let code = vec![PUSH1, 0x01, PUSH1, 0x02, PUSH1, 0x03, STOP];
+        assert_eq!(simulate_eof_function_run(&code, &ft, &mut SimulatedStack::new(), 100), Err(EOFError::StackOverflow));
+        assert_eq!(simulate_eof_run(&code, &mut SimulatedStack::new(), 100), Ok(4));
+
+        let ft = FunctionType { max_stack_height: 3, ..ft };
+        assert_eq!(simulate_eof_function_run(&code, &ft, &mut SimulatedStack::new(), 100), Ok(4));
+    }
 
+    #[test]
+    fn test_minimize_failing_container() {
+        let mut code = [PUSH0, POP].repeat(20);
+        code.extend([INVALID, PUSH1, 0x01, POP, STOP]);
+        let bytecode = create_valid_eof_bytecode(vec![code, vec![PUSH0, POP, STOP]], Some(vec![0xAA; 16]));
         let container = parse_eof_container(&bytecode).unwrap();
-        assert_eq!(validate_eof_container(&container), Err(EOFError::MalformedSectionHeader));
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(INVALID)));
+
+        let minimized = minimize_failing_container(&container).unwrap();
+        assert_eq!(validate_eof_container(&minimized), Err(EOFError::InvalidOpcode(INVALID)));
+        assert_eq!(minimized.code_sections().collect::<Vec<_>>(), vec![&[INVALID][..]]);
+        assert_eq!(declared_data_size(&minimized), 0);
+        assert!(minimized.to_bytes().len() < 16);
+
+        let valid = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], None)).unwrap();
+        assert_eq!(minimize_failing_container(&valid), None);
     }
 
-    // --- Instruction Set Expansion Tests ---
+    #[test]
+    fn test_eofcreate_target_must_be_initcode() {
+        let factory = vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP];
+        let valid = parse_eof_container(&create_eof_bytecode_with_containers(vec![factory.clone()], vec![create_initcode_bytecode()], None)).unwrap();
+        assert_eq!(validate_eof_container(&valid), Ok(()));
+
+        let runtime = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let invalid = parse_eof_container(&create_eof_bytecode_with_containers(vec![factory], vec![runtime], None)).unwrap();
+        assert_eq!(validate_eof_container(&invalid), Err(EOFError::EofCreateTargetNotInitcode(0)));
+    }
 
     #[test]
-    fn test_simulate_rjump() {
-        let mut pc = 0;
-        let mut stack = SimulatedStack::new();
-        // RJUMP +0 (relative to next instruction) -> jump to opcode at index 3 (immediately after RJUMP)
-        let code = vec![RJUMP, 0x00, 0x00, 0xFF, 0x01]; // RJUMP +0, INVALID, ADD
-        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
-        assert_eq!(pc, 3); // PC should be 3 (0 + 3 + 0)
-        // Next step should execute 0xFF (INVALID, caught by default step)
-        // simulate_eof_step(&code, &mut pc, &mut stack).unwrap_err(); // Should be INVALID if it had error handling for it
+    fn test_stack_heights_dupn_index_in_range() {
+        let types = [FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 0 }];
+        assert_eq!(validate_stack_heights(&[PUSH0, PUSH0, DUPN, 0x01, STOP], &types, 0), Ok(3));
+        assert_eq!(validate_stack_heights(&[PUSH0, PUSH0, DUPN, 0x02, STOP], &types, 0), Err(EOFError::StackUnderflow));
     }
 
+    // Hand-written regression vectors for the EIP-3540/3670/4200 rules, encoded in this
+    // prototype's header layout (kind byte + u16 size per section). They are not the
+    // upstream ethereum-tests EOF vectors, whose header encoding differs, and do not
+    // show conformance with them.
+    const PROTOTYPE_VECTORS: &[(&str, &str, Result<(), EOFError>)] = &[
+        ("minimal STOP", "ef0001 010004 020001 00 00800000 00", Ok(())),
+        ("PUSH0 POP STOP with data", "ef0001 010004 020003 040002 00 00800001 5f5000 aabb", Ok(())),
+        ("RJUMP to next instruction", "ef0001 010004 020004 00 00800000 e0000000", Ok(())),
+        ("RJUMPI on PUSH0", "ef0001 010004 020005 00 00800001 5fe1000000", Ok(())),
+        ("CALLF to returning function", "ef0001 010008 020004 020001 00 00800000 00000000 e3000100 e4", Ok(())),
+        ("legacy bytecode", "6000", Err(EOFError::NotEOF)),
+        ("wrong magic", "ef01 01 010004 020001 00 00800000 00", Err(EOFError::InvalidMagic)),
+        ("version 2", "ef0002 010004 020001 00 00800000 00", Err(EOFError::InvalidVersion(2))),
+        ("missing terminator", "ef0001 010004 020001", Err(EOFError::MissingTerminator)),
+        ("INVALID opcode", "ef0001 010004 020001 00 00800000 fe", Err(EOFError::InvalidOpcode(INVALID))),
+        ("JUMP", "ef0001 010004 020003 00 00800001 600056", Err(EOFError::JumpDestForbidden(JUMP))),
+        ("truncated PUSH1", "ef0001 010004 020001 00 00800000 60", Err(EOFError::TruncatedPushData)),
+        ("RJUMP past section end", "ef0001 010004 020004 00 00800000 e0000500", Err(EOFError::InvalidJumpDestination(0))),
+    ];
+
     #[test]
-    fn test_simulate_rjump_backward() {
-        // Opcode at 0, RJUMP at 1 (offset -2).
-        // If `pc` is 1 for RJUMP, then `new_pc = (1 + 3) + (-2) = 2`. It should jump to PUSH1.
-        let code = vec![0x01, RJUMP, 0xFF, 0xFE, PUSH1, 0x01]; // ADD (0), RJUMP (1), <offset bytes> (2,3), PUSH1 (4), 0x01 (5)
-        let mut pc = 1; // RJUMP is at index 1
-        let mut stack = SimulatedStack::new();
-        stack.push(1).unwrap();
-        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
-        // New PC should be (1 + 3) + (-2) = 4 - 2 = 2. It should jump to byte 2 (0xFF).
-        assert_eq!(pc, 2); // Corrected calculation: (start of RJUMP opcode + instruction length) + offset
+    fn test_prototype_vectors() {
+        for (name, hex, expected) in PROTOTYPE_VECTORS {
+            let bytecode = decode_hex(&hex.replace(' ', "")).unwrap();
+            assert_eq!(&parse_and_validate(&bytecode).map(|_| ()), expected, "vector: {}", name);
+        }
     }
 
     #[test]
-    fn test_simulate_rjump_out_of_bounds() {
-        let mut pc = 0;
-        let mut stack = SimulatedStack::new();
-        // RJUMP to a location outside the code
-        let code = vec![RJUMP, 0x7F, 0xFF]; // RJUMP +32767
-        // Current PC=0. Next PC = (0 + 3) + 32767 = 32770. This will be out of bounds.
-        simulate_eof_step(&code, &mut pc, &mut stack).unwrap(); // It doesn't error on the step if the target PC is out of bounds, only next step
-        assert_eq!(pc, 32770);
-        // The *next* simulate_eof_step call would then return UnexpectedEndOfInput.
+    fn test_validate_declared_max_stack() {
+        let code = [PUSH0, PUSH0, PUSH0, POP, POP, POP, STOP];
+        let over_declared = [FunctionType { inputs: 0, outputs: NON_RETURNING_FUNCTION, max_stack_height: 5 }];
+        assert_eq!(validate_declared_max_stack(&code, &over_declared, 0), Err(EOFError::DeclaredMaxStackMismatch { declared: 5, computed: 3 }));
+        let exact = [FunctionType { max_stack_height: 3, ..over_declared[0] }];
+        assert_eq!(validate_declared_max_stack(&code, &exact, 0), Ok(()));
     }
 
+    #[test]
+    fn test_uses_eof_features() {
+        let legacy_like = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, 0x01, PUSH1, 0x02, ADD, STOP]], None)).unwrap();
+        assert!(!legacy_like.uses_eof_features());
+        let with_callf = parse_eof_container(&create_valid_eof_bytecode(vec![vec![CALLF, 0x00, 0x01, STOP], vec![RETF]], None)).unwrap();
+        assert!(with_callf.uses_eof_features());
+        // A PUSH immediate that happens to equal CALLF is not an instruction
+        let immediate_only = parse_eof_container(&create_valid_eof_bytecode(vec![vec![PUSH1, CALLF, STOP]], None)).unwrap();
+        assert!(!immediate_only.uses_eof_features());
+    }
 
     #[test]
-    fn test_simulate_rjumpi_true() {
-        let mut pc = 0;
+    fn test_simulate_verify_boundary() {
+        let code = [PUSH2, 0x60, 0x01, STOP];
+        let config = SimulateConfig::verifying_boundaries(&code);
         let mut stack = SimulatedStack::new();
-        stack.push(1).unwrap(); // Condition is true
-        // RJUMPI +2
-        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01]; // RJUMPI +2, INVALID, ADD
+        let mut pc = 1; // Inside the PUSH2 immediate, which reads as PUSH1 0x01
+        assert_eq!(simulate_eof_step_cfg(&code, &mut pc, &mut stack, &config), Err(EOFError::JumpToImmediate(1)));
+        assert_eq!((pc, stack.len()), (1, 0));
+
         simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
-        // New PC should be (0 + 3) + 2 = 5.
-        assert_eq!(pc, 5);
-        assert_eq!(stack.len(), 0);
+        assert_eq!(pc, 3);
+
+        let mut pc = 0;
+        simulate_eof_step_cfg(&code, &mut pc, &mut stack, &config).unwrap();
+        assert_eq!(pc, 3);
     }
 
     #[test]
-    fn test_simulate_rjumpi_false() {
-        let mut pc = 0;
+    fn test_validate_eof_deep_reports_paths() {
+        let runtime = create_valid_eof_bytecode(vec![vec![STOP]], None);
+        let broken = create_valid_eof_bytecode(vec![vec![INVALID]], None);
+        let deploy = vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00];
+        let middle = create_eof_bytecode_with_containers(vec![deploy.clone(), vec![PUSH0, PUSH0, RETURNCONTRACT, 0x01]], vec![runtime.clone(), broken], None);
+        let root = create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, PUSH0, PUSH0, EOFCREATE, 0x00, STOP]], vec![middle], None);
+        let container = parse_eof_container(&root).unwrap();
+
+        assert_eq!(validate_eof_container(&container), Err(EOFError::InvalidOpcode(INVALID)));
+        assert_eq!(validate_eof_deep(&container), Err(vec![(vec![0, 1], EOFError::InvalidOpcode(INVALID))]));
+
+        let valid = parse_eof_container(&create_eof_bytecode_with_containers(vec![vec![PUSH0, PUSH0, RETURNCONTRACT, 0x00]], vec![runtime], None)).unwrap();
+        assert_eq!(validate_eof_deep(&valid), Ok(()));
+    }
+
+    #[test]
+    fn test_rewrite_offsets_after_insert() {
+        // RJUMP at 0 stays before offset 6; RJUMP at 3 spans it, landing on STOP at 8
+        let code = vec![RJUMP, 0x00, 0x00, RJUMP, 0x00, 0x02, PUSH0, POP, STOP];
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![code.clone()], None)).unwrap();
+        let mut edited = container.replace_code_section(0, [&code[..6], &[PUSH0], &code[6..]].concat()).unwrap();
+        edited.rewrite_offsets_after_insert(0, 6, 1).unwrap();
+        assert_eq!(edited.code_sections().next().unwrap(), &[RJUMP, 0x00, 0x00, RJUMP, 0x00, 0x03, PUSH0, PUSH0, POP, STOP]);
+        assert_eq!(validate_eof_container(&edited), Ok(()));
+
+        // A backward RJUMPI after the insertion point targeting code before it
+        let code = vec![PUSH0, RJUMPI, 0xFF, 0xFC, STOP];
+        let container = parse_eof_container(&create_valid_eof_bytecode(vec![code.clone()], None)).unwrap();
+        let mut edited = container.replace_code_section(0, [&code[..1], &[PUSH0], &code[1..]].concat()).unwrap();
+        edited.rewrite_offsets_after_insert(0, 1, 1).unwrap();
+        assert_eq!(edited.code_sections().next().unwrap(), &[PUSH0, PUSH0, RJUMPI, 0xFF, 0xFB, STOP]);
+
+        assert_eq!(edited.rewrite_offsets_after_insert(0, 6, 1), Err(EOFError::UnexpectedEndOfInput));
+        assert_eq!(edited.rewrite_offsets_after_insert(1, 0, 1), Err(EOFError::InvalidSectionIndex(1)));
+    }
+
+    #[test]
+    fn test_type_only_container_missing_code_section() {
+        let container = EOFContainer {
+            header: EOFHeader { version: EOF_VERSION, section_headers: vec![SectionHeader { kind: SectionKind::Type, size: 4 }] },
+            sections: vec![vec![0x00, 0x80, 0x00, 0x00]],
+        };
+        assert_eq!(container.function_types().unwrap().len(), 1);
+        assert_eq!(validate_structure(&container), Err(EOFError::MissingCodeSection));
+        assert_eq!(validate_eof_container(&container), Err(EOFError::MissingCodeSection));
+        assert_eq!(validate_eof_reader(container.to_bytes().as_slice()), Err(EOFError::MissingCodeSection));
+    }
+
+    #[test]
+    fn test_section_accessors() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, POP, STOP], vec![RETF]], Some(vec![0xAA, 0xBB]));
+        let container = parse_eof_container(&bytecode).unwrap();
+        assert_eq!(container.type_section(), Some(&container.sections[0][..]));
+        assert_eq!(container.type_section().unwrap().len(), 8);
+        assert_eq!(container.code_sections().collect::<Vec<_>>(), vec![&[PUSH0, POP, STOP][..], &[RETF][..]]);
+        assert_eq!(container.data_section(), Some(&[0xAA, 0xBB][..]));
+
+        let without_data = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP]], None)).unwrap();
+        assert_eq!(without_data.data_section(), None);
+    }
+
+    #[test]
+    fn test_parse_random_bytes_never_panics() {
+        // xorshift64: deterministic, so any failure is reproducible
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..10_000 {
+            let mut bytes: Vec<u8> = (0..next() % 64).map(|_| next() as u8).collect();
+            // Most inputs get a valid prefix and plausible headers so parsing reaches the bodies
+            if next() % 4 != 0 {
+                bytes = vec![0xEF, 0x00, EOF_VERSION];
+                let mut body_len = 0;
+                for _ in 0..1 + next() % 4 {
+                    let size = next() % 9;
+                    bytes.extend([(next() % 5) as u8, 0x00, size as u8]);
+                    body_len += size;
+                }
+                bytes.push(0x00);
+                bytes.extend((0..body_len + next() % 2).map(|_| next() as u8));
+            }
+            if let Ok(container) = parse_eof_container(&bytes) {
+                let _ = validate_eof_container(&container);
+                let _ = container.to_bytes();
+            }
+            let _ = parse_eof_container_at(&bytes);
+        }
+    }
+
+    #[test]
+    fn test_simulated_stack_limit() {
+        let mut stack = SimulatedStack::new_with_limit(2);
+        stack.push(1).unwrap();
+        stack.push(2).unwrap();
+        assert_eq!(stack.push(3), Err(EOFError::StackOverflow));
+        assert_eq!(SimulatedStack::new().limit(), 1024);
+    }
+
+    #[test]
+    fn test_simulate_callf_checks_callee_max_stack() {
+        let mut container = EOFContainer::single_function(vec![PUSH1, 0x01, PUSH1, 0x02, STOP]).unwrap();
+        // Takes one input and declares room for two more items, though it only pushes one
+        let callee = FunctionType { inputs: 1, outputs: 1, max_stack_height: 3 };
+        container.push_code(vec![PUSH1, 0x03, ADD, RETF], callee).unwrap();
+        let container = container.replace_code_section(0, vec![PUSH1, 0x01, PUSH1, 0x02, CALLF, 0x00, 0x01, STOP]).unwrap();
+
+        let mut stack = SimulatedStack::new_with_limit(4);
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 100), Ok(7));
+        // Two items at the call plus the callee's declared growth of two exceed a limit of 3
+        let mut stack = SimulatedStack::new_with_limit(3);
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 100), Err(EOFError::StackOverflow));
+        assert_eq!(stack.depth(), 2);
+    }
+
+    #[test]
+    fn test_simulate_container_run_enforces_frame_max_stack() {
+        // The callee declares room for its input only, but pushes a second item
+        let mut container = EOFContainer::single_function(vec![PUSH1, 0x01, STOP]).unwrap();
+        container.push_code(vec![PUSH1, 0x02, ADD, RETF], FunctionType { inputs: 1, outputs: 1, max_stack_height: 1 }).unwrap();
+        let container = container.replace_code_section(0, vec![PUSH1, 0x01, CALLF, 0x00, 0x01, STOP]).unwrap();
         let mut stack = SimulatedStack::new();
-        stack.push(0).unwrap(); // Condition is false
-        // RJUMPI +2
-        let code = vec![RJUMPI, 0x00, 0x02, 0xFF, 0x01]; // RJUMPI +2, INVALID, ADD
-        simulate_eof_step(&code, &mut pc, &mut stack).unwrap();
-        // New PC should just advance by 3 (opcode + 2 immediate)
-        assert_eq!(pc, 3);
-        assert_eq!(stack.len(), 0);
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 100), Err(EOFError::StackOverflow));
+        assert_eq!(stack.depth(), 2);
+
+        // The entry frame is held to its own declared height as well
+        let container = container.replace_code_section(0, vec![PUSH1, 0x01, PUSH1, 0x02, STOP]).unwrap();
+        let mut stack = SimulatedStack::new();
+        assert_eq!(simulate_eof_container_run(&container, &mut stack, 100), Err(EOFError::StackOverflow));
     }
 
     #[test]
-    fn test_simulate_rjumpi_stack_underflow() {
+    fn test_simulate_step_callf_checks_callee_max_stack() {
+        let callee = FunctionType { inputs: 1, outputs: 1, max_stack_height: 3 };
+        let config = SimulateConfig { function_types: vec![FunctionType::from_bytes([0, NON_RETURNING_FUNCTION, 0, 2]), callee], ..SimulateConfig::default() };
+        let code = [CALLF, 0x00, 0x01, STOP];
+
+        let mut stack = SimulatedStack::new_with_limit(3);
+        stack.push(1).unwrap();
+        stack.push(2).unwrap();
         let mut pc = 0;
-        let mut stack = SimulatedStack::new(); // Empty stack
-        let code = vec![RJUMPI, 0x00, 0x02, 0xFF];
-        assert_eq!(simulate_eof_step(&code, &mut pc, &mut stack), Err(EOFError::StackUnderflow));
+        assert_eq!(simulate_eof_step_cfg(&code, &mut pc, &mut stack, &config), Err(EOFError::StackOverflow));
+        assert_eq!(pc, 0);
+
+        // Without Type entries the call is stepped over unchecked
+        assert_eq!(simulate_eof_step(&code, &mut pc, &mut stack), Ok(()));
+        assert_eq!((pc, stack.depth()), (3, 2));
+    }
+
+    #[test]
+    fn test_validate_eof() {
+        let caller = vec![PUSH0, CALLF, 0x00, 0x01, POP, STOP];
+        let callee = vec![PUSH0, ADD, RETF];
+        let mut valid = parse_eof_container(&create_valid_eof_bytecode(vec![caller, callee], None)).unwrap();
+        // Entry: 0 inputs, non-returning, max 1; callee: 1 input, 1 output, max 2
+        valid.sections[0] = vec![0x00, NON_RETURNING_FUNCTION, 0x00, 0x01, 0x01, 0x01, 0x00, 0x02];
+        assert_eq!(validate_eof(&valid.to_bytes()), Ok(valid.clone()));
+
+        // Callee over-declares its max_stack_height by one
+        let mut over_declared = valid.clone();
+        over_declared.sections[0][7] = 0x03;
+        assert_eq!(validate_eof_container(&over_declared), Ok(()));
+        assert_eq!(validate_eof(&over_declared.to_bytes()), Err(EOFError::DeclaredMaxStackMismatch { declared: 3, computed: 2 }));
+
+        // Earlier stages win over stack analysis
+        assert_eq!(validate_eof(&[0xEF, 0x00, 0x02]), Err(EOFError::InvalidVersion(2)));
+    }
+
+    #[test]
+    fn test_validate_eof_type_code_mismatch() {
+        let bytecode = create_valid_eof_bytecode(vec![vec![PUSH0, CALLF, 0x00, 0x01, STOP], vec![POP, RETF]], None);
+        let mut container = parse_eof_container(&bytecode).unwrap();
+        // Entry max 1024 exceeds MAX_STACK_HEIGHT; callee max 0 cannot hold its 1 input
+        container.sections[0] = vec![0x00, NON_RETURNING_FUNCTION, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00];
+        assert_eq!(validate_eof(&container.to_bytes()), Err(EOFError::TypeCodeMismatch { type_index: 0 }));
+        container.sections[0][2..4].copy_from_slice(&[0x00, 0x01]);
+        assert_eq!(validate_eof(&container.to_bytes()), Err(EOFError::TypeCodeMismatch { type_index: 1 }));
+        container.sections[0][7] = 0x01;
+        assert_eq!(validate_eof(&container.to_bytes()), Ok(container.clone()));
+
+        // The entry point must not be a returning function
+        container.sections[0][1] = 0x00;
+        assert_eq!(validate_eof(&container.to_bytes()), Err(EOFError::TypeCodeMismatch { type_index: 0 }));
+        // A section that uses RETF must not be declared non-returning
+        let mut unreachable_retf = parse_eof_container(&create_valid_eof_bytecode(vec![vec![STOP], vec![RETF]], None)).unwrap();
+        unreachable_retf.sections[0] = vec![0x00, NON_RETURNING_FUNCTION, 0x00, 0x00, 0x00, NON_RETURNING_FUNCTION, 0x00, 0x00];
+        assert_eq!(validate_eof(&unreachable_retf.to_bytes()), Err(EOFError::TypeCodeMismatch { type_index: 1 }));
     }
 }